@@ -0,0 +1,811 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::{
+    fs::{inode, is_invalid_name_char, is_outside_root, EntryType, FileSystemEntry, Roots},
+    git::Tracked,
+};
+
+/// A single step of applying the pending changes. All paths are absolute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    CreateFolder(String),
+    Rename { from: String, to: String },
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::CreateFolder(path) => write!(f, "create folder {path}"),
+            Operation::Rename { from, to } => write!(f, "rename {from} to {to}"),
+        }
+    }
+}
+
+/// The operations needed to give every entry its new path, in the order they have to be run.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub operations: Vec<Operation>,
+    /// What every renamed entry looked like when it was scanned, see [`verify`].
+    pub sources: Vec<Source>,
+}
+
+/// A renamed entry, with its metadata as it was recorded when its folder was scanned.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub path: String,
+    /// The absolute path the entry ends up at once the whole plan has run.
+    pub target: String,
+    pub entry_type: EntryType,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub inode: Option<u64>,
+}
+
+impl Source {
+    fn new(path: String, target: String, entry: &FileSystemEntry) -> Self {
+        Self {
+            path,
+            target,
+            entry_type: entry.entry_type,
+            size: entry.size,
+            modified: entry.modified,
+            inode: entry.inode,
+        }
+    }
+
+    /// How the entry changed on disk since it was scanned, if it did. Only the identity of
+    /// folders is checked, as their modification time changes whenever their content does.
+    fn check(&self) -> Option<SourceChangeKind> {
+        let Ok(metadata) = Path::new(&self.path).symlink_metadata() else {
+            return Some(SourceChangeKind::Removed);
+        };
+        let is_same_type = match self.entry_type {
+            EntryType::File => metadata.is_file(),
+            EntryType::Folder => metadata.is_dir(),
+        };
+        if !is_same_type || self.inode.is_some_and(|ino| inode(&metadata) != Some(ino)) {
+            Some(SourceChangeKind::Replaced)
+        } else if matches!(self.entry_type, EntryType::File)
+            && (metadata.len() != self.size || metadata.modified().ok() != self.modified)
+        {
+            Some(SourceChangeKind::Modified)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanError {
+    pub path: String,
+    pub kind: PlanErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanErrorKind {
+    EmptyName,
+    InvalidName,
+    OutsideRoot,
+    IntoItself,
+    DuplicateTarget,
+    TargetExists,
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            PlanErrorKind::EmptyName => "the new name is empty",
+            PlanErrorKind::InvalidName => "the new name is not a valid name",
+            PlanErrorKind::OutsideRoot => "the new path is outside of the opened folders",
+            PlanErrorKind::IntoItself => "a folder cannot be moved into itself",
+            PlanErrorKind::DuplicateTarget => "another entry has the same new path",
+            PlanErrorKind::TargetExists => "the new path already exists",
+        };
+        write!(f, "{}: {}", self.path, reason)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceChange {
+    pub path: String,
+    pub kind: SourceChangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceChangeKind {
+    Removed,
+    Replaced,
+    Modified,
+}
+
+impl Display for SourceChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            SourceChangeKind::Removed => "it was removed or renamed",
+            SourceChangeKind::Replaced => "it was replaced by another entry",
+            SourceChangeKind::Modified => "its size or modification time changed",
+        };
+        write!(f, "{}: {}", self.path, reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApplyError {
+    pub operation: Operation,
+    pub completed: usize,
+    pub reason: String,
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Could not {} ({}), {} operations were completed before that",
+            self.operation, self.reason, self.completed
+        )
+    }
+}
+
+/// Validates the new path of every changed entry and works out how to get there.
+///
+/// Entries inside a renamed folder move along with it, and renames that need a path that another
+/// rename frees up are run after that rename. Renames that form a cycle are broken up by first
+/// moving one of the entries to a temporary name. Folders that don't exist yet are created right
+/// before the first rename that needs them.
+pub fn plan(
+    roots: &Roots,
+    entries: &[FileSystemEntry],
+    allow_outside_root: bool,
+) -> Result<Plan, Vec<PlanError>> {
+    let exists = |path: &str| {
+        roots
+            .absolute(path)
+            .is_some_and(|path| Path::new(&path).exists())
+    };
+    let absolute = |path: &str| {
+        roots
+            .absolute(path)
+            .expect("Planned paths are in one of the opened folders")
+    };
+
+    let targets = resolve_targets(entries);
+    let mut target_counts: HashMap<&str, usize> = HashMap::new();
+    for target in &targets {
+        *target_counts.entry(target.path.as_str()).or_default() += 1;
+    }
+    let og_paths = entries
+        .iter()
+        .map(|entry| entry.og_path.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut errors = Vec::new();
+    let mut renames = Vec::new();
+    let mut sources = Vec::new();
+    for (entry, target) in entries.iter().zip(&targets) {
+        if target.path == target.moved_along {
+            continue;
+        }
+        let name = &target.path[target
+            .path
+            .rfind('/')
+            .expect("All paths contain a separator")
+            + 1..];
+        let error = if name.is_empty() {
+            Some(PlanErrorKind::EmptyName)
+        } else if name == "." || name == ".." || name.contains(is_invalid_name_char) {
+            Some(PlanErrorKind::InvalidName)
+        } else if roots
+            .split(&target.path)
+            .is_none_or(|(_, relative_path)| !allow_outside_root && is_outside_root(relative_path))
+        {
+            Some(PlanErrorKind::OutsideRoot)
+        } else if matches!(entry.entry_type, EntryType::Folder)
+            && is_within(&target.path, &target.moved_along)
+        {
+            Some(PlanErrorKind::IntoItself)
+        } else if target_counts[target.path.as_str()] > 1 {
+            Some(PlanErrorKind::DuplicateTarget)
+        } else if !og_paths.contains(target.path.as_str()) && exists(&target.path) {
+            Some(PlanErrorKind::TargetExists)
+        } else {
+            None
+        };
+        match error {
+            Some(kind) => errors.push(PlanError {
+                path: entry.og_path.clone(),
+                kind,
+            }),
+            None => {
+                sources.push(Source::new(
+                    absolute(&entry.og_path),
+                    absolute(&target.path),
+                    entry,
+                ));
+                renames.push((entry.og_path.clone(), target.path.clone()));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut operations = Vec::new();
+    let mut steps = Vec::new();
+    for (from, to) in order_renames(renames, exists) {
+        // The opened folders themselves and the folders above them are never created.
+        let folders = ancestors(&to)
+            .filter(|folder| {
+                roots
+                    .split(folder)
+                    .is_some_and(|(_, rest)| !rest.is_empty() && !rest.ends_with("/.."))
+            })
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        for folder in folders.into_iter().rev() {
+            if !exists_after(&steps, &folder, exists) {
+                operations.push(Operation::CreateFolder(absolute(&folder)));
+                steps.push((None, folder));
+            }
+        }
+        operations.push(Operation::Rename {
+            from: absolute(&from),
+            to: absolute(&to),
+        });
+        steps.push((Some(from), to));
+    }
+    Ok(Plan {
+        operations,
+        sources,
+    })
+}
+
+/// Where an entry ends up once all changes are applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Target {
+    /// The path the entry gets by only moving along with its renamed parent folders.
+    moved_along: String,
+    /// The path the entry has to end up at.
+    path: String,
+}
+
+/// Works out where every entry ends up. An entry inside a renamed folder moves along with it, so
+/// both its original path and a new path that still points into the original folder are
+/// rewritten to point into the renamed folder instead.
+fn resolve_targets(entries: &[FileSystemEntry]) -> Vec<Target> {
+    let mut order = (0..entries.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| entries[i].depth);
+
+    let mut renamed_folders: HashMap<&str, String> = HashMap::new();
+    let mut targets = vec![Target::default(); entries.len()];
+    for i in order {
+        let entry = &entries[i];
+        let parent =
+            ancestors(&entry.og_path).find_map(|folder| renamed_folders.get_key_value(folder));
+        let target = match parent {
+            Some((folder, new_folder)) => Target {
+                moved_along: rebase(&entry.og_path, folder, new_folder)
+                    .expect("The folder is an ancestor of the entry"),
+                path: rebase(&entry.path, folder, new_folder).unwrap_or_else(|| entry.path.clone()),
+            },
+            None => Target {
+                moved_along: entry.og_path.clone(),
+                path: entry.path.clone(),
+            },
+        };
+        if matches!(entry.entry_type, EntryType::Folder) && target.path != entry.og_path {
+            renamed_folders.insert(&entry.og_path, target.path.clone());
+        }
+        targets[i] = target;
+    }
+    targets
+}
+
+/// Orders renames so that no rename targets a path that is still taken by the source of another
+/// rename, and nothing is moved into a folder before that folder has been moved into place.
+/// Sources inside a folder that has been moved are rewritten to the folder's new path, and cycles
+/// are broken by moving one entry of the cycle to a temporary name first.
+fn order_renames<F>(renames: Vec<(String, String)>, exists: F) -> Vec<(String, String)>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut pending = Pending::default();
+    let mut ordered = Vec::with_capacity(renames.len());
+    for (from, to) in renames {
+        pending.push(from, to);
+    }
+
+    let mut temporary_count = 0;
+    while pending.remaining > 0 {
+        let mut progressed = false;
+        for i in 0..pending.renames.len() {
+            let Some((_, to)) = &pending.renames[i] else {
+                continue;
+            };
+            if pending.blocker(to).is_none() {
+                let (from, to) = pending.take(i);
+                pending.move_sources(&from, &to);
+                ordered.push((from, to));
+                progressed = true;
+            }
+        }
+        if !progressed {
+            let (from, to) = pending.take(pending.find_cycle());
+            let temporary = loop {
+                temporary_count += 1;
+                let (folder, name) = from
+                    .rsplit_once('/')
+                    .expect("All paths contain a separator");
+                let temporary = format!("{folder}/.mareto-{temporary_count}-{name}");
+                if !exists(&temporary) && !pending.sources.contains_key(&temporary) {
+                    break temporary;
+                }
+            };
+            pending.push(temporary.clone(), to);
+            pending.move_sources(&from, &temporary);
+            ordered.push((from, temporary));
+        }
+    }
+    ordered
+}
+
+/// The renames [`order_renames`] still has to order, indexed by their current source and target.
+#[derive(Default)]
+struct Pending {
+    renames: Vec<Option<(String, String)>>,
+    sources: BTreeMap<String, usize>,
+    targets: HashMap<String, usize>,
+    remaining: usize,
+}
+
+impl Pending {
+    fn push(&mut self, from: String, to: String) {
+        self.sources.insert(from.clone(), self.renames.len());
+        self.targets.insert(to.clone(), self.renames.len());
+        self.renames.push(Some((from, to)));
+        self.remaining += 1;
+    }
+
+    fn take(&mut self, i: usize) -> (String, String) {
+        let (from, to) = self.renames[i].take().expect("The rename is still pending");
+        self.sources.remove(&from);
+        self.targets.remove(&to);
+        self.remaining -= 1;
+        (from, to)
+    }
+
+    /// The rename that has to run before something can be moved to `to`, and whether that is
+    /// because its source is `to` rather than because it moves a folder `to` is in into place.
+    fn blocker(&self, to: &str) -> Option<(usize, bool)> {
+        if let Some(&i) = self.sources.get(to) {
+            return Some((i, true));
+        }
+        ancestors(to)
+            .find_map(|folder| self.targets.get(folder))
+            .map(|&i| (i, false))
+    }
+
+    /// Rewrites the sources of all renames inside the folder `from` after it was moved to `to`.
+    fn move_sources(&mut self, from: &str, to: &str) {
+        let inside = self
+            .sources
+            .range(format!("{from}/")..format!("{from}0"))
+            .map(|(source, _)| source.clone())
+            .collect::<Vec<_>>();
+        for source in inside {
+            let i = self.sources.remove(&source).expect("The source is pending");
+            let moved = format!("{to}{}", &source[from.len()..]);
+            if let Some((from, _)) = &mut self.renames[i] {
+                from.clone_from(&moved);
+            }
+            self.sources.insert(moved, i);
+        }
+    }
+
+    /// Finds a rename that is part of a cycle of renames waiting for each other and is waiting
+    /// for its target to be freed up. Must only be called when every pending rename is blocked.
+    fn find_cycle(&self) -> usize {
+        let blocker = |i: usize| {
+            let (_, to) = self.renames[i]
+                .as_ref()
+                .expect("The rename is still pending");
+            self.blocker(to).expect("Every pending rename is blocked")
+        };
+        let mut visited = Vec::new();
+        let mut i = self
+            .renames
+            .iter()
+            .position(Option::is_some)
+            .expect("There is a pending rename");
+        while !visited.contains(&i) {
+            visited.push(i);
+            i = blocker(i).0;
+        }
+        let cycle_start = visited.iter().position(|&j| j == i).unwrap_or_default();
+        visited[cycle_start..]
+            .iter()
+            .copied()
+            .find(|&j| blocker(j).1)
+            .expect("Folders cannot be moved into each other in a cycle")
+    }
+}
+
+/// Whether `path` exists after running the planned steps, each of which moves a path or creates
+/// a folder if it has no source.
+fn exists_after<F>(steps: &[(Option<String>, String)], path: &str, exists: F) -> bool
+where
+    F: Fn(&str) -> bool,
+{
+    let mut path = path.to_owned();
+    for (from, to) in steps.iter().rev() {
+        if let Some(moved_from) = from.as_ref().and_then(|from| rebase(&path, to, from)) {
+            if path == *to {
+                return true;
+            }
+            path = moved_from;
+        } else if from.is_none() && path == *to {
+            return true;
+        } else if from.as_ref().is_some_and(|from| is_within(&path, from)) {
+            return false;
+        }
+    }
+    exists(&path)
+}
+
+/// All folders `path` is in, from the innermost one outwards, not including the opened folder.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.rmatch_indices('/')
+        .map(move |(i, _)| &path[..i])
+        .filter(|folder| !folder.is_empty())
+}
+
+/// Whether `path` is `folder` or inside of it.
+fn is_within(path: &str, folder: &str) -> bool {
+    rebase(path, folder, "").is_some()
+}
+
+/// Replaces the `from` folder at the start of `path` with `to`, if `path` is in it.
+fn rebase(path: &str, from: &str, to: &str) -> Option<String> {
+    path.strip_prefix(from)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .map(|rest| format!("{to}{rest}"))
+}
+
+/// Checks that every entry the plan renames is still the one that was scanned, so that nothing
+/// replaced, removed or renamed by another program in the meantime gets renamed by mistake.
+pub fn verify(plan: &Plan) -> Result<(), Vec<SourceChange>> {
+    let changes = plan
+        .sources
+        .iter()
+        .filter_map(|source| {
+            source.check().map(|kind| SourceChange {
+                path: source.path.clone(),
+                kind,
+            })
+        })
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        Ok(())
+    } else {
+        Err(changes)
+    }
+}
+
+/// Runs the operations of a plan in order, stopping at the first one that fails. Existing paths
+/// are never overwritten. Renames that cross devices, such as between opened folders on different
+/// drives, copy the entry and then delete the original.
+///
+/// Entries in `tracked` are moved with `git mv` instead, as long as they stay in the same working
+/// tree, so that git sees them as renamed.
+pub fn execute(plan: &Plan, tracked: &mut Tracked) -> Result<(), ApplyError> {
+    for (completed, operation) in plan.operations.iter().enumerate() {
+        let result = match operation {
+            Operation::CreateFolder(path) => std::fs::create_dir_all(path),
+            Operation::Rename { from, to } => {
+                if Path::new(to).symlink_metadata().is_ok() {
+                    Err(std::io::ErrorKind::AlreadyExists.into())
+                } else {
+                    rename(from, to, tracked)
+                }
+            }
+        };
+        result.map_err(|err| ApplyError {
+            operation: operation.clone(),
+            completed,
+            reason: err.to_string(),
+        })?;
+    }
+    Ok(())
+}
+
+fn rename(from: &str, to: &str, tracked: &mut Tracked) -> std::io::Result<()> {
+    if tracked.mv(from, to)? {
+        return Ok(());
+    }
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            move_by_copying(Path::new(from), Path::new(to))
+        }
+        result => result,
+    }
+}
+
+/// Copies an entry and deletes the original. If the copy fails, what was copied so far is
+/// removed again and the original is left untouched.
+fn move_by_copying(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Err(err) = copy_all(from, to) {
+        let _ = remove_all(to);
+        return Err(err);
+    }
+    remove_all(from)
+}
+
+fn copy_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        std::fs::set_permissions(to, metadata.permissions())
+    } else if metadata.is_symlink() {
+        copy_symlink(from, to)
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::copy(from, to).map(|_| ())
+}
+
+fn remove_all(path: &Path) -> std::io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(&[("/a", "/b")], &[("/a", "/b")])]
+    #[case(&[("/a", "/b"), ("/b", "/c")], &[("/b", "/c"), ("/a", "/b")])]
+    #[case(
+        &[("/a", "/b"), ("/b", "/a")],
+        &[("/a", "/.mareto-1-a"), ("/b", "/a"), ("/.mareto-1-a", "/b")],
+    )]
+    #[case(
+        &[("/d/a", "/d/b"), ("/d/b", "/d/c"), ("/d/c", "/d/a"), ("/x", "/y")],
+        &[("/x", "/y"), ("/d/a", "/d/.mareto-1-a"), ("/d/c", "/d/a"), ("/d/b", "/d/c"), ("/d/.mareto-1-a", "/d/b")],
+    )]
+    #[case(
+        &[("/p/a", "/q/b"), ("/p", "/q")],
+        &[("/p", "/q"), ("/q/a", "/q/b")],
+    )]
+    #[case(
+        &[("/p", "/q"), ("/p/a", "/a")],
+        &[("/p", "/q"), ("/q/a", "/a")],
+    )]
+    #[case(
+        &[("/p/a", "/q/b"), ("/p/b", "/q/a"), ("/p", "/q")],
+        &[("/p", "/q"), ("/q/a", "/q/.mareto-1-a"), ("/q/b", "/q/a"), ("/q/.mareto-1-a", "/q/b")],
+    )]
+    fn test_order_renames(#[case] pending: &[(&str, &str)], #[case] expected: &[(&str, &str)]) {
+        assert_eq!(
+            order_renames(renames(pending), |_| false),
+            renames(expected)
+        );
+    }
+
+    #[test]
+    fn test_resolve_targets_moves_children_along() {
+        let entries = [
+            FileSystemEntry::for_test("/photos/a.jpg", "/photos/b.jpg", EntryType::File),
+            FileSystemEntry::for_test("/photos/c.jpg", "/photos/c.jpg", EntryType::File),
+            FileSystemEntry::for_test("/photos/old", "/photos/new", EntryType::Folder),
+            FileSystemEntry::for_test("/photos/old/d.jpg", "/e.jpg", EntryType::File),
+            FileSystemEntry::for_test("/photos", "/Photos 2024", EntryType::Folder),
+        ];
+        let targets = resolve_targets(&entries)
+            .into_iter()
+            .map(|target| (target.moved_along, target.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            renames(&[
+                ("/Photos 2024/a.jpg", "/Photos 2024/b.jpg"),
+                ("/Photos 2024/c.jpg", "/Photos 2024/c.jpg"),
+                ("/Photos 2024/old", "/Photos 2024/new"),
+                ("/Photos 2024/new/d.jpg", "/e.jpg"),
+                ("/photos", "/Photos 2024"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_plan_renames_folder_and_children() {
+        let root = "/mareto-test-root-that-does-not-exist";
+        let entries = [
+            FileSystemEntry::for_test("/photos", "/Photos 2024", EntryType::Folder),
+            FileSystemEntry::for_test("/photos/a.jpg", "/photos/b.jpg", EntryType::File),
+            FileSystemEntry::for_test("/photos/c.jpg", "/photos/c.jpg", EntryType::File),
+            FileSystemEntry::for_test("/d.jpg", "/photos/d.jpg", EntryType::File),
+        ];
+        let operations = plan(&Roots::new([root.to_owned()]), &entries, false)
+            .unwrap()
+            .operations;
+        let create_folder = |path: &str| Operation::CreateFolder(format!("{root}{path}"));
+        let rename = |from: &str, to: &str| Operation::Rename {
+            from: format!("{root}{from}"),
+            to: format!("{root}{to}"),
+        };
+        assert_eq!(
+            operations,
+            vec![
+                rename("/photos", "/Photos 2024"),
+                rename("/Photos 2024/a.jpg", "/Photos 2024/b.jpg"),
+                create_folder("/photos"),
+                rename("/d.jpg", "/photos/d.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_reports_children_that_collide_after_moving_along() {
+        let entries = [
+            FileSystemEntry::for_test("/photos", "/Photos 2024", EntryType::Folder),
+            FileSystemEntry::for_test("/photos/a.jpg", "/photos/b.jpg", EntryType::File),
+            FileSystemEntry::for_test("/photos/b.jpg", "/photos/b.jpg", EntryType::File),
+        ];
+        let roots = Roots::new(["/mareto-test-root-that-does-not-exist".to_owned()]);
+        let errors = plan(&roots, &entries, false).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![PlanError {
+                path: "/photos/a.jpg".to_owned(),
+                kind: PlanErrorKind::DuplicateTarget,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_order_renames_skips_existing_temporary_names() {
+        let ordered = order_renames(renames(&[("/a", "/b"), ("/b", "/a")]), |path| {
+            path == "/.mareto-1-a"
+        });
+        assert_eq!(ordered[0], ("/a".to_owned(), "/.mareto-2-a".to_owned()));
+    }
+
+    #[test]
+    fn test_plan_moves_between_roots() {
+        let roots = Roots::new([
+            "/mareto-test-root-that-does-not-exist/Downloads".to_owned(),
+            "/mareto-test-drive-that-does-not-exist".to_owned(),
+        ]);
+        // The label of a folder is its name, so the second folder is moved to by that name.
+        let a = FileSystemEntry::for_test(
+            "/Downloads/a.jpg",
+            "/mareto-test-drive-that-does-not-exist/a.jpg",
+            EntryType::File,
+        );
+        let b = FileSystemEntry::for_test("/Downloads/b.jpg", "/elsewhere/b.jpg", EntryType::File);
+        let errors = plan(&roots, &[a.clone(), b], true).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![PlanError {
+                path: "/Downloads/b.jpg".to_owned(),
+                kind: PlanErrorKind::OutsideRoot,
+            }]
+        );
+
+        let operations = plan(&roots, &[a], false).unwrap().operations;
+        assert_eq!(
+            operations,
+            vec![Operation::Rename {
+                from: "/mareto-test-root-that-does-not-exist/Downloads/a.jpg".to_owned(),
+                to: "/mareto-test-drive-that-does-not-exist/a.jpg".to_owned(),
+            }]
+        );
+
+        // A path outside of its folder is only allowed if moves outside are allowed.
+        let c = FileSystemEntry::for_test(
+            "/Downloads/c.jpg",
+            "/Downloads/../mareto-test-drive-that-does-not-exist/c.jpg",
+            EntryType::File,
+        );
+        let errors = plan(&roots, std::slice::from_ref(&c), false).unwrap_err();
+        assert_eq!(errors[0].kind, PlanErrorKind::OutsideRoot);
+        let operations = plan(&roots, &[c], true).unwrap().operations;
+        assert_eq!(
+            operations.last(),
+            Some(&Operation::Rename {
+                from: "/mareto-test-root-that-does-not-exist/Downloads/c.jpg".to_owned(),
+                to: "/mareto-test-root-that-does-not-exist/mareto-test-drive-that-does-not-exist/c.jpg"
+                    .to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_move_by_copying() {
+        let dir = std::env::temp_dir().join(format!("mareto-test-{}", std::process::id()));
+        let from = dir.join("from");
+        std::fs::create_dir_all(from.join("inner")).unwrap();
+        std::fs::write(from.join("inner").join("a.txt"), "a").unwrap();
+        let to = dir.join("to");
+
+        move_by_copying(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            std::fs::read_to_string(to.join("inner").join("a.txt")).unwrap(),
+            "a"
+        );
+
+        assert!(move_by_copying(&from, &dir.join("other")).is_err());
+        assert!(!dir.join("other").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_reports_changed_sources() {
+        let dir = std::env::temp_dir().join(format!("mareto-verify-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            std::fs::write(dir.join(name), "content").unwrap();
+        }
+        let root = dir.to_str().unwrap().to_owned();
+        let roots = Roots::new([root.clone()]);
+        let mut entries = crate::fs::get_entries_for_roots(&roots).unwrap();
+        for entry in &mut entries {
+            entry.set_path(format!("{}_new", entry.og_path));
+        }
+        let plan = plan(&roots, &entries, false).unwrap();
+        assert_eq!(verify(&plan), Ok(()));
+
+        std::fs::write(dir.join("a.txt"), "changed content").unwrap();
+        std::fs::remove_file(dir.join("b.txt")).unwrap();
+        std::fs::write(dir.join("c.new"), "content").unwrap();
+        std::fs::rename(dir.join("c.new"), dir.join("c.txt")).unwrap();
+        let mut changes = verify(&plan).unwrap_err();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut expected = vec![
+            (format!("{root}/a.txt"), SourceChangeKind::Modified),
+            (format!("{root}/b.txt"), SourceChangeKind::Removed),
+        ];
+        if cfg!(unix) {
+            expected.push((format!("{root}/c.txt"), SourceChangeKind::Replaced));
+        }
+        assert_eq!(
+            changes,
+            expected
+                .into_iter()
+                .map(|(path, kind)| SourceChange { path, kind })
+                .collect::<Vec<_>>()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
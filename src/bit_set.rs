@@ -1,39 +1,137 @@
-#[derive(Debug, Clone)]
+use rayon::prelude::*;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A fixed-size set of bits stored in `u64` words.
+///
+/// Bits past `size` in the last word are always kept cleared so that word-level
+/// operations such as [`BitSet::count_ones`] never need to mask them out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct BitSet {
-    pub size: usize,
-    pub chunks: Vec<u8>,
+    size: usize,
+    words: Vec<u64>,
 }
 
 impl BitSet {
     pub fn new() -> Self {
         Self {
-            chunks: Vec::new(),
+            words: Vec::new(),
             size: 0,
         }
     }
 
+    /// Resizes the set to `new_size` bits. Bits that are added are cleared.
     pub fn resize(&mut self, new_size: usize) {
         self.size = new_size;
-        self.chunks.resize(new_size.div_ceil(8), u8::MAX);
+        self.words.resize(new_size.div_ceil(WORD_BITS), 0);
+        self.clear_trailing_bits();
     }
 
     pub fn set_bit(&mut self, bit_index: usize, value: bool) {
         if bit_index >= self.size {
             return;
         }
-        let chunk = bit_index / 8;
-        let bit = bit_index % 8;
-        self.chunks[chunk] &= !(1_u8 << bit);
-        self.chunks[chunk] |= (value as u8) << bit;
+        let word = bit_index / WORD_BITS;
+        let bit = bit_index % WORD_BITS;
+        self.words[word] &= !(1_u64 << bit);
+        self.words[word] |= (value as u64) << bit;
     }
 
     pub fn is_bit_set(&self, bit_index: usize) -> bool {
         if bit_index >= self.size {
             return false;
         }
-        let chunk = bit_index / 8;
-        let bit = bit_index % 8;
-        (self.chunks[chunk] & (1 << bit)) > 0
+        let word = bit_index / WORD_BITS;
+        let bit = bit_index % WORD_BITS;
+        (self.words[word] & (1 << bit)) > 0
+    }
+
+    /// Resizes the set to the length of `items` and sets every bit to the result of `op` for the
+    /// item with the same index. Each word is computed independently, so the items are
+    /// evaluated in parallel.
+    pub fn par_set_from<T, F>(&mut self, items: &[T], op: F)
+    where
+        T: Sync,
+        F: Fn(&T) -> bool + Sync,
+    {
+        self.resize(items.len());
+        self.words
+            .par_iter_mut()
+            .zip(items.par_chunks(WORD_BITS))
+            .for_each(|(word, chunk)| {
+                *word = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (bit, item)| word | ((op(item) as u64) << bit));
+            });
+    }
+
+    pub fn set_all(&mut self) {
+        self.words.fill(u64::MAX);
+        self.clear_trailing_bits();
+    }
+
+    pub fn clear_all(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Keeps only the bits that are also set in `other`.
+    pub fn and(&mut self, other: &BitSet) {
+        debug_assert_eq!(self.size, other.size);
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(left, right)| *left &= right);
+    }
+
+    /// Sets every bit that is set in `other`.
+    #[allow(dead_code)]
+    pub fn or(&mut self, other: &BitSet) {
+        debug_assert_eq!(self.size, other.size);
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(left, right)| *left |= right);
+    }
+
+    /// Clears every bit that is set in `other`.
+    pub fn and_not(&mut self, other: &BitSet) {
+        debug_assert_eq!(self.size, other.size);
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(left, right)| *left &= !right);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates over the indices of all set bits in ascending order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+
+    fn clear_trailing_bits(&mut self) {
+        let used_bits = self.size % WORD_BITS;
+        if used_bits > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1_u64 << used_bits) - 1;
+            }
+        }
     }
 }
 
@@ -43,62 +141,143 @@ mod tests {
 
     use rstest::rstest;
 
+    fn bit_set_with(size: usize, bits: &[usize]) -> BitSet {
+        let mut bit_set = BitSet::new();
+        bit_set.resize(size);
+        for bit in bits {
+            bit_set.set_bit(*bit, true);
+        }
+        bit_set
+    }
+
     #[rstest]
     #[case(1, 1)]
-    #[case(8, 1)]
-    #[case(9, 2)]
-    #[case(16, 2)]
-    #[case(17, 3)]
-    fn test_resize(#[case] bits: usize, #[case] bytes: usize) {
+    #[case(64, 1)]
+    #[case(65, 2)]
+    #[case(128, 2)]
+    #[case(129, 3)]
+    fn test_resize(#[case] bits: usize, #[case] words: usize) {
         let mut bit_set = BitSet::new();
         bit_set.resize(bits);
         assert_eq!(bit_set.size, bits);
-        assert_eq!(bit_set.chunks.len(), bytes);
+        assert_eq!(bit_set.words.len(), words);
+        assert_eq!(bit_set.count_ones(), 0);
+    }
+
+    #[rstest]
+    #[case(10, 70)]
+    #[case(70, 10)]
+    #[case(64, 3)]
+    fn test_resize_keeps_bits_past_size_cleared(#[case] from: usize, #[case] to: usize) {
+        let mut bit_set = BitSet::new();
+        bit_set.resize(from);
+        bit_set.set_all();
+        bit_set.resize(to);
+        assert_eq!(bit_set.count_ones(), from.min(to));
+        assert_eq!(bit_set.iter_ones().last(), Some(from.min(to) - 1));
     }
 
     #[rstest]
     #[case(0, vec![], vec![])]
-    #[case(8, vec![], vec![0])]
-    #[case(16, vec![], vec![0, 0])]
-    #[case(24, vec![], vec![0, 0, 0])]
-    #[case(6, vec![(7, true), (8, true)], vec![0])]
+    #[case(64, vec![], vec![0])]
+    #[case(128, vec![], vec![0, 0])]
+    #[case(6, vec![(7, true), (64, true)], vec![0])]
     #[case(8, vec![(1, true)], vec![0b00000010])]
-    #[case(16, vec![(9, true), (11, true)], vec![0, 0b00001010])]
-    #[case(24, vec![(1, true), (23, true) ], vec![0b00000010, 0, 0b10000000])]
+    #[case(128, vec![(65, true), (67, true)], vec![0, 0b00001010])]
+    #[case(192, vec![(1, true), (191, true) ], vec![0b00000010, 0, 1 << 63])]
     #[case(8, vec![(1, true), (2, true), (3, true), (2, false)], vec![0b00001010])]
     fn test_set_bit(
         #[case] size: usize,
         #[case] bits_to_set: Vec<(usize, bool)>,
-        #[case] expected: Vec<u8>,
+        #[case] expected: Vec<u64>,
     ) {
         let mut bit_set = BitSet::new();
         bit_set.resize(size);
         for (bit, value) in bits_to_set {
             bit_set.set_bit(bit, value);
         }
-        assert_eq!(bit_set.chunks, expected);
+        assert_eq!(bit_set.words, expected);
     }
 
     #[rstest]
     #[case(vec![], 0, vec![])]
-    #[case(vec![0], 8, vec![])]
-    #[case(vec![0, 0], 16, vec![])]
-    #[case(vec![0, 0, 0], 24, vec![])]
+    #[case(vec![0], 64, vec![])]
     #[case(vec![0b10000000], 8, vec![7])]
-    #[case(vec![0, 0b00010000], 16, vec![12])]
-    #[case(vec![0, 0, 0b00000010], 24, vec![17])]
-    #[case(vec![0b00001110], 8, vec![1,2,3])]
-    #[case(vec![0, 0b11100000], 16, vec![13,14,15])]
-    #[case(vec![0b10000001, 0b10000001, 0b10000001], 24, vec![0, 7, 8, 15, 16, 23])]
+    #[case(vec![0, 0b00010000], 128, vec![68])]
+    #[case(vec![0b00001110], 8, vec![1, 2, 3])]
+    #[case(vec![1 | 1 << 63, 1 | 1 << 63], 128, vec![0, 63, 64, 127])]
     #[case(vec![0b10000010], 7, vec![1])]
-    #[case(vec![0b00100001, 0b11111111], 9, vec![0, 5, 8])]
-    #[case(vec![0b00000001, 0, 0b11111111], 16, vec![0])]
-    fn test_is_bit_set(#[case] chunks: Vec<u8>, #[case] size: usize, #[case] expected: Vec<usize>) {
-        let total_bits = chunks.len() * 8;
-        let bit_set = BitSet { chunks, size };
+    #[case(vec![0b00000001, 0, u64::MAX], 128, vec![0])]
+    fn test_is_bit_set(#[case] words: Vec<u64>, #[case] size: usize, #[case] expected: Vec<usize>) {
+        let total_bits = words.len() * WORD_BITS;
+        let bit_set = BitSet { words, size };
         let actual = (0..total_bits)
             .filter(|i| bit_set.is_bit_set(*i))
             .collect::<Vec<_>>();
         assert_eq!(actual, expected);
     }
+
+    #[rstest]
+    #[case(0, vec![])]
+    #[case(10, vec![0, 9])]
+    #[case(130, vec![0, 63, 64, 65, 127, 128, 129])]
+    fn test_iter_ones(#[case] size: usize, #[case] bits: Vec<usize>) {
+        let bit_set = bit_set_with(size, &bits);
+        assert_eq!(bit_set.iter_ones().collect::<Vec<_>>(), bits);
+        assert_eq!(bit_set.count_ones(), bits.len());
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(64)]
+    #[case(100)]
+    fn test_set_all_and_clear_all(#[case] size: usize) {
+        let mut bit_set = bit_set_with(size, &[]);
+        bit_set.set_all();
+        assert_eq!(bit_set.count_ones(), size);
+        assert_eq!(
+            bit_set.iter_ones().collect::<Vec<_>>(),
+            (0..size).collect::<Vec<_>>()
+        );
+        bit_set.clear_all();
+        assert_eq!(bit_set.count_ones(), 0);
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(5)]
+    #[case(64)]
+    #[case(200)]
+    fn test_par_set_from(#[case] size: usize) {
+        let items = (0..size).collect::<Vec<_>>();
+        let mut bit_set = bit_set_with(3, &[0, 1, 2]);
+        bit_set.par_set_from(&items, |i| i % 3 == 0);
+        assert_eq!(bit_set.size, size);
+        assert_eq!(
+            bit_set.iter_ones().collect::<Vec<_>>(),
+            items.into_iter().filter(|i| i % 3 == 0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_bulk_operations() {
+        let left = bit_set_with(100, &[1, 2, 70, 99]);
+        let right = bit_set_with(100, &[2, 3, 70, 98]);
+
+        let mut and = left.clone();
+        and.and(&right);
+        assert_eq!(and.iter_ones().collect::<Vec<_>>(), vec![2, 70]);
+
+        let mut or = left.clone();
+        or.or(&right);
+        assert_eq!(
+            or.iter_ones().collect::<Vec<_>>(),
+            vec![1, 2, 3, 70, 98, 99]
+        );
+
+        let mut and_not = left.clone();
+        and_not.and_not(&right);
+        assert_eq!(and_not.iter_ones().collect::<Vec<_>>(), vec![1, 99]);
+    }
 }
@@ -0,0 +1,59 @@
+/// The command line arguments, passed on to [`crate::mareto::Mareto`] as its flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// The name of a preset to load on start.
+    pub preset: Option<String>,
+    /// The folders to open on start instead of picking them.
+    pub folders: Vec<String>,
+}
+
+pub const USAGE: &str = "Usage: mareto [--preset <NAME>] [FOLDER]...";
+
+impl Flags {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut flags = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--preset" => {
+                    let name = args.next().ok_or("Missing preset name after --preset")?;
+                    flags.preset = Some(name);
+                }
+                _ => match arg.strip_prefix("--preset=") {
+                    Some(name) => flags.preset = Some(name.to_owned()),
+                    None if arg.starts_with("--") => {
+                        return Err(format!("Unexpected argument \"{arg}\""))
+                    }
+                    None => flags.folders.push(arg),
+                },
+            }
+        }
+        Ok(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[], Ok((None, vec![])))]
+    #[case(&["--preset", "camera dumps"], Ok((Some("camera dumps"), vec![])))]
+    #[case(&["--preset=weekly"], Ok((Some("weekly"), vec![])))]
+    #[case(&["photos", "--preset", "weekly"], Ok((Some("weekly"), vec!["photos"])))]
+    #[case(&["/home/me/photos"], Ok((None, vec!["/home/me/photos"])))]
+    #[case(&["photos", "/mnt/drive"], Ok((None, vec!["photos", "/mnt/drive"])))]
+    #[case(&["--preset"], Err(()))]
+    #[case(&["--verbose"], Err(()))]
+    fn test_parse(#[case] args: &[&str], #[case] expected: Result<(Option<&str>, Vec<&str>), ()>) {
+        let flags = Flags::parse(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            flags.map_err(|_| ()),
+            expected.map(|(preset, folders)| Flags {
+                preset: preset.map(str::to_owned),
+                folders: folders.iter().map(|folder| folder.to_string()).collect(),
+            })
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use iced::{Size, Theme};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mareto::Error,
+    shortcuts::Shortcut,
+    state::{DepthLimit, DisplayType, FilterOptions, Options, SortingOption},
+};
+
+/// The user's settings, read from `config.toml` in the `mareto` folder of the platform's config
+/// directory. Everything but the shortcuts is saved again when Mareto is closed. Unknown keys
+/// and shortcut actions, such as ones written by a newer version, are ignored.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub options: OptionsConfig,
+    pub filters: FiltersConfig,
+    pub window: Option<WindowConfig>,
+    /// Shortcuts that replace the default ones, keyed by the name of the action, e.g.
+    /// `find_next = "Ctrl+G"`.
+    pub shortcuts: BTreeMap<String, Shortcut>,
+    /// The most recently opened folders, the latest first.
+    pub recent_folders: Vec<String>,
+}
+
+const MAX_RECENT_FOLDERS: usize = 10;
+
+/// The folder the config and preset files are stored in.
+pub fn config_folder() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("mareto"))
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        config_folder().map(|folder| folder.join("config.toml"))
+    }
+
+    /// Reads the config file. If there is no config file yet, the default config is returned.
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(Error::Config(error.to_string())),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        std::fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// Takes over the current options and filters, to be saved for the next session.
+    pub fn update(&mut self, options: &Options, filters: &FilterOptions) {
+        self.options = OptionsConfig::from(options);
+        self.filters = FiltersConfig::from(filters);
+    }
+
+    /// Sets the options and filters to the ones that were saved.
+    pub fn apply(&self, options: &mut Options, filters: &mut FilterOptions) {
+        self.options.apply(options);
+        self.filters.apply(filters);
+    }
+
+    /// Moves a folder to the front of the recent folders, dropping the oldest one if the list
+    /// is full.
+    pub fn add_recent_folder(&mut self, folder: String) {
+        self.recent_folders.retain(|recent| *recent != folder);
+        self.recent_folders.insert(0, folder);
+        self.recent_folders.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        toml::from_str(contents).map_err(|error| Error::Config(error.to_string()))
+    }
+
+    fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("The config can always be serialized")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionsConfig {
+    pub sorting: SortingOption,
+    pub secondary_sorting: SortingOption,
+    pub directories_first: bool,
+    pub display_type: DisplayType,
+    pub remove_empty: bool,
+    pub preview_changes: bool,
+    pub watch_folders: bool,
+    pub use_git: bool,
+    /// The name of the theme, as shown in the theme selection.
+    pub theme: String,
+}
+
+impl OptionsConfig {
+    fn apply(&self, options: &mut Options) {
+        options.sorting.selected = Some(self.sorting);
+        options.secondary_sorting.selected = Some(self.secondary_sorting);
+        options.directories_first = self.directories_first;
+        options.display_type.selected = Some(self.display_type);
+        options.remove_empty = self.remove_empty;
+        options.preview_changes = self.preview_changes;
+        options.watch_folders = self.watch_folders;
+        options.use_git = self.use_git;
+        // An unknown theme keeps the default one.
+        if let Some(theme) = Theme::ALL
+            .iter()
+            .find(|theme| theme.to_string() == self.theme)
+        {
+            options.theme.selected = Some(theme.clone());
+        }
+    }
+}
+
+impl Default for OptionsConfig {
+    fn default() -> Self {
+        Self::from(&Options::default())
+    }
+}
+
+impl From<&Options> for OptionsConfig {
+    fn from(options: &Options) -> Self {
+        Self {
+            sorting: options.sorting.selected.unwrap_or(SortingOption::NoSorting),
+            secondary_sorting: options
+                .secondary_sorting
+                .selected
+                .unwrap_or(SortingOption::NoSorting),
+            directories_first: options.directories_first,
+            display_type: options
+                .display_type
+                .selected
+                .unwrap_or(DisplayType::RelativePath),
+            remove_empty: options.remove_empty,
+            preview_changes: options.preview_changes,
+            watch_folders: options.watch_folders,
+            use_git: options.use_git,
+            theme: options
+                .theme
+                .selected
+                .as_ref()
+                .map(|theme| theme.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FiltersConfig {
+    pub input: String,
+    pub use_regex: bool,
+    pub case_sensitive: bool,
+    pub min_depth: DepthLimit,
+    pub max_depth: DepthLimit,
+    pub show_files: bool,
+    pub show_folders: bool,
+}
+
+impl FiltersConfig {
+    pub fn apply(&self, filters: &mut FilterOptions) {
+        let input = &mut filters.filter_input.state;
+        input.input = self.input.clone();
+        input.use_regex = self.use_regex;
+        input.case_sensitive = self.case_sensitive;
+        filters.min_depth.state = self.min_depth.clone();
+        filters.max_depth.state = self.max_depth.clone();
+        filters.normalize_max_depth();
+        filters.show_files.state = self.show_files;
+        filters.show_folders.state = self.show_folders;
+    }
+}
+
+impl Default for FiltersConfig {
+    fn default() -> Self {
+        Self::from(&FilterOptions::default())
+    }
+}
+
+impl From<&FilterOptions> for FiltersConfig {
+    fn from(filters: &FilterOptions) -> Self {
+        let input = &filters.filter_input.state;
+        Self {
+            input: input.input.clone(),
+            use_regex: input.use_regex,
+            case_sensitive: input.case_sensitive,
+            min_depth: filters.min_depth.state.clone(),
+            max_depth: filters.max_depth.state.clone(),
+            show_files: filters.show_files.state,
+            show_folders: filters.show_folders.state,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl From<WindowConfig> for Size {
+    fn from(window: WindowConfig) -> Self {
+        Size::new(window.width, window.height)
+    }
+}
+
+impl From<Size> for WindowConfig {
+    fn from(size: Size) -> Self {
+        Self {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse("[shortcuts]\nfind_next = \"Ctrl+G\"\n").unwrap();
+        assert_eq!(
+            config.shortcuts.get("find_next"),
+            Some(&"Ctrl+G".parse().unwrap())
+        );
+        assert_eq!(config.options, OptionsConfig::default());
+
+        assert!(Config::parse("").unwrap().shortcuts.is_empty());
+        assert!(Config::parse("[shortcuts]\nfind_next = \"Ctrl+Banana\"\n").is_err());
+        assert!(Config::parse("[options]\nsorting = \"sideways\"\n").is_err());
+        assert!(Config::parse("[shortcuts").is_err());
+
+        let config = Config::parse(
+            "future = 1\n[options]\nwatch_folders = true\nfuture = 2\n[filters]\nfuture = 3\n\
+             [filters.min_depth]\nis_active = true\nfuture = 4\n\
+             [window]\nwidth = 1.0\nheight = 2.0\nfuture = 5\n[shortcuts]\nfly = \"Ctrl+G\"\n",
+        )
+        .unwrap();
+        assert!(config.options.watch_folders);
+        assert!(config.filters.min_depth.is_active);
+        assert!(config.shortcuts.contains_key("fly"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut options = Options::default();
+        options.sorting.selected = Some(SortingOption::SortNaturalDescending);
+        options.display_type.selected = Some(DisplayType::JustName);
+        options.remove_empty = true;
+        options.watch_folders = true;
+        options.use_git = true;
+        options.theme.selected = Some(Theme::TokyoNight);
+        let mut filters = FilterOptions::default();
+        filters.filter_input.state.input = r"\.jpg$".to_owned();
+        filters.filter_input.state.use_regex = true;
+        filters.max_depth.state = DepthLimit {
+            is_active: true,
+            limit: Some(2),
+        };
+        filters.show_folders.state = false;
+
+        let mut config = Config::default();
+        config.update(&options, &filters);
+        config.window = Some(Size::new(1600.0, 900.0).into());
+        config
+            .shortcuts
+            .insert("undo".to_owned(), "Alt+Backspace".parse().unwrap());
+        let config = Config::parse(&config.to_toml()).unwrap();
+
+        let mut loaded_options = Options::default();
+        let mut loaded_filters = FilterOptions::default();
+        config.apply(&mut loaded_options, &mut loaded_filters);
+        assert_eq!(
+            OptionsConfig::from(&loaded_options),
+            OptionsConfig::from(&options)
+        );
+        assert_eq!(loaded_options.theme.selected, Some(Theme::TokyoNight));
+        let loaded_filters = FiltersConfig::from(&loaded_filters);
+        assert_eq!(loaded_filters.input, r"\.jpg$");
+        assert!(loaded_filters.use_regex);
+        assert_eq!(loaded_filters.max_depth.limit, Some(2));
+        assert!(!loaded_filters.show_folders);
+        assert_eq!(config.window, Some(Size::new(1600.0, 900.0).into()));
+        assert_eq!(
+            config.shortcuts.get("undo"),
+            Some(&"Alt+Backspace".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_add_recent_folder() {
+        let mut config = Config::default();
+        for i in 0..12 {
+            config.add_recent_folder(format!("/folder{i}"));
+        }
+        config.add_recent_folder("/folder5".to_owned());
+        assert_eq!(config.recent_folders.len(), MAX_RECENT_FOLDERS);
+        assert_eq!(
+            config.recent_folders[..3],
+            ["/folder5", "/folder11", "/folder10"]
+        );
+        assert_eq!(config.recent_folders.last().unwrap(), "/folder2");
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Range;
+
+/// Lines with more character pairs than this in their differing middle part are not diffed
+/// character by character. Their whole middle part is reported as changed instead.
+const MAX_CELLS: usize = 1 << 16;
+
+/// Returns the ranges of characters that differ between an old and a new line, as character
+/// indices into the old and the new line respectively.
+///
+/// Characters that are part of a longest common subsequence of both lines are considered
+/// unchanged, so a rename like `IMG_0001.JPG` to `img_0001.jpg` only marks the changed letters.
+pub fn changed_chars(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old = old.chars().collect::<Vec<_>>();
+    let new = new.chars().collect::<Vec<_>>();
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut old_changed = vec![true; old_middle.len()];
+    let mut new_changed = vec![true; new_middle.len()];
+    if old_middle.len() * new_middle.len() <= MAX_CELLS {
+        mark_common(old_middle, new_middle, &mut old_changed, &mut new_changed);
+    }
+    (
+        to_ranges(&old_changed, prefix),
+        to_ranges(&new_changed, prefix),
+    )
+}
+
+/// Clears the flags of the characters that are part of a longest common subsequence.
+fn mark_common(old: &[char], new: &[char], old_changed: &mut [bool], new_changed: &mut [bool]) {
+    // `lengths[i][j]` is the length of the longest common subsequence of `old[i..]` and
+    // `new[j..]`.
+    let width = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            old_changed[i] = false;
+            new_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+}
+
+fn to_ranges(changed: &[bool], offset: usize) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, _) in changed.iter().enumerate().filter(|(_, changed)| **changed) {
+        match ranges.last_mut() {
+            Some(range) if range.end == offset + i => range.end += 1,
+            _ => ranges.push(offset + i..offset + i + 1),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/a/b.txt", "/a/b.txt", vec![], vec![])]
+    #[case("/a/b.txt", "/a/c.txt", vec![3..4], vec![3..4])]
+    #[case("/IMG_01.JPG", "/img_01.jpg", vec![1..4, 8..11], vec![1..4, 8..11])]
+    #[case("/b.txt", "/a/b.txt", vec![], vec![1..3])]
+    #[case("/photo.jpg", "/ph.jpg", vec![3..6], vec![])]
+    #[case("/ä.txt", "/ö.txt", vec![1..2], vec![1..2])]
+    #[case("", "/new", vec![], vec![0..4])]
+    fn test_changed_chars(
+        #[case] old: &str,
+        #[case] new: &str,
+        #[case] old_ranges: Vec<Range<usize>>,
+        #[case] new_ranges: Vec<Range<usize>>,
+    ) {
+        assert_eq!(changed_chars(old, new), (old_ranges, new_ranges));
+    }
+
+    #[test]
+    fn test_long_lines_are_changed_as_a_whole() {
+        let old = format!("/{}", "a".repeat(300));
+        let new = format!("/{}", "b".repeat(300));
+        let (old_ranges, new_ranges) = changed_chars(&old, &new);
+        assert_eq!(old_ranges, new_ranges);
+        assert_eq!(old_ranges.len(), 1);
+        assert_eq!(old_ranges[0], 1..301);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{path::Path, time::SystemTime};
 
 use crate::mareto::Error;
 
@@ -15,6 +15,271 @@ pub struct FileSystemEntry {
     pub last_sep: usize,
     pub depth: usize,
     pub entry_type: EntryType,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Identifies the file on disk, so that an entry replaced since the scan can be told apart.
+    /// Only known on Unix.
+    pub inode: Option<u64>,
+    /// The index of the opened folder the entry is in, see [`Roots`].
+    pub root: usize,
+}
+
+impl FileSystemEntry {
+    /// Sets the path the entry should be renamed to, keeping `last_sep` in line with it.
+    pub fn set_path(&mut self, path: String) {
+        self.last_sep = last_separator(&path);
+        self.path = path;
+    }
+
+    pub fn reset_path(&mut self) {
+        self.set_path(self.og_path.clone());
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.path != self.og_path
+    }
+
+    pub fn og_name(&self) -> &str {
+        &self.og_path[last_separator(&self.og_path) + 1..]
+    }
+}
+
+#[cfg(test)]
+impl FileSystemEntry {
+    /// An entry of the first opened folder that is to be moved from `og_path` to `path`.
+    pub fn for_test(og_path: &str, path: &str, entry_type: EntryType) -> Self {
+        Self {
+            og_path: og_path.to_owned(),
+            path: path.to_owned(),
+            last_sep: last_separator(path),
+            depth: og_path.matches('/').count(),
+            entry_type,
+            size: 0,
+            modified: None,
+            inode: None,
+            root: 0,
+        }
+    }
+}
+
+/// The opened folders. With more than one, the path of every entry starts with the label of its
+/// folder, like `/Downloads/a.jpg`, so an entry is moved to another folder by changing that first
+/// component.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Roots {
+    pub folders: Vec<String>,
+    /// The name of each folder, made unique by appending a number.
+    pub labels: Vec<String>,
+}
+
+impl Roots {
+    pub fn new(folders: impl IntoIterator<Item = String>) -> Self {
+        let mut roots = Self::default();
+        for folder in folders {
+            roots.push(folder);
+        }
+        roots
+    }
+
+    /// Adds a folder, unless it is open already.
+    pub fn push(&mut self, folder: String) {
+        let folder = match folder.trim_end_matches('/') {
+            "" => "/".to_owned(),
+            trimmed => trimmed.to_owned(),
+        };
+        if self.folders.contains(&folder) {
+            return;
+        }
+        let name = folder
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("root");
+        let mut label = name.to_owned();
+        let mut count = 1;
+        while self.labels.contains(&label) {
+            count += 1;
+            label = format!("{name} ({count})");
+        }
+        self.folders.push(folder);
+        self.labels.push(label);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folders.is_empty()
+    }
+
+    /// What the paths of the entries in the given folder start with.
+    pub fn prefix(&self, root: usize) -> String {
+        if self.folders.len() > 1 {
+            format!("/{}", self.labels[root])
+        } else {
+            String::new()
+        }
+    }
+
+    /// Splits the path of an entry into the index of its folder and the path relative to that
+    /// folder. Returns `None` if several folders are open and the path doesn't start with a label.
+    pub fn split<'a>(&self, path: &'a str) -> Option<(usize, &'a str)> {
+        if self.folders.len() <= 1 {
+            return (!self.folders.is_empty()).then_some((0, path));
+        }
+        let label = path.strip_prefix('/')?.split('/').next()?;
+        let root = self.labels.iter().position(|l| l == label)?;
+        Some((root, &path[1 + label.len()..]))
+    }
+
+    /// Turns the path of an entry into an absolute path.
+    pub fn absolute(&self, path: &str) -> Option<String> {
+        let (root, relative_path) = self.split(path)?;
+        let folder = &self.folders[root];
+        Some(if is_outside_root(relative_path) {
+            normalize_relative_path(&format!("{folder}{relative_path}"))
+        } else {
+            format!("{folder}{relative_path}")
+        })
+    }
+
+    /// Turns an absolute path into the path of an entry, if it is in one of the folders.
+    pub fn entry_path(&self, path: &str) -> Option<String> {
+        let is_in_folder = self.folders.iter().any(|folder| {
+            path.strip_prefix(folder.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        });
+        is_in_folder.then(|| self.relative(path, 0))
+    }
+
+    /// Turns an absolute path into the path of an entry. A path that is in none of the folders is
+    /// made relative to the folder with the index `fallback`.
+    pub fn relative(&self, path: &str, fallback: usize) -> String {
+        let root = self
+            .folders
+            .iter()
+            .enumerate()
+            .filter(|(_, folder)| {
+                path.strip_prefix(folder.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|(_, folder)| folder.len())
+            .map_or(fallback, |(root, _)| root);
+        format!(
+            "{}{}",
+            self.prefix(root),
+            relative_to_root(&self.folders[root], path)
+        )
+    }
+
+    /// Lexically normalizes the path of an entry like [`normalize_relative_path`]. With several
+    /// folders a leading label is kept as is, so that `..` components after it point outside of
+    /// that folder instead of cancelling the label out.
+    pub fn normalize(&self, path: &str) -> String {
+        if self.folders.len() > 1 {
+            if let Some((label, rest)) = path.trim_start_matches('/').split_once('/') {
+                if self.labels.iter().any(|l| l == label) {
+                    return format!("/{label}{}", normalize_relative_path(&format!("/{rest}")));
+                }
+            }
+        }
+        normalize_relative_path(path)
+    }
+}
+
+fn last_separator(path: &str) -> usize {
+    path.rfind('/')
+        .expect("There must be at least one separator")
+}
+
+/// Lexically normalizes a path relative to the opened folder. Empty and `.` folder components
+/// are removed and `..` is resolved where possible, while the final component is kept as is so
+/// that an empty or invalid name is not silently turned into a different path.
+///
+/// The result always starts with a separator, and starts with `/..` if it points outside of the
+/// opened folder.
+pub fn normalize_relative_path(path: &str) -> String {
+    let (folders, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let mut components = Vec::new();
+    for component in folders.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if components.last().is_some_and(|last| *last != "..") => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components.push(name);
+    format!("/{}", components.join("/"))
+}
+
+/// Turns an absolute path into a path relative to `root`, using `..` components if it points
+/// outside of it.
+pub fn relative_to_root(root: &str, path: &str) -> String {
+    let root_components = root
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>();
+    let path = normalize_relative_path(path);
+    let components = path[1..].split('/').collect::<Vec<_>>();
+    let common = root_components
+        .iter()
+        .zip(components.iter())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(components.len() - 1);
+    let relative = std::iter::repeat_n("..", root_components.len() - common)
+        .chain(components[common..].iter().copied())
+        .collect::<Vec<_>>();
+    format!("/{}", relative.join("/"))
+}
+
+/// Splits absolute paths into the deepest folder that contains all of them and their paths
+/// relative to that folder. Returns `None` if they only have the file system root in common.
+pub fn common_parent(paths: &[String]) -> Option<(String, Vec<String>)> {
+    let mut parent = paths.first()?.rsplit_once('/')?.0;
+    for path in &paths[1..] {
+        while !path
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('/'))
+        {
+            parent = parent.rsplit_once('/')?.0;
+        }
+    }
+    if parent.is_empty() {
+        return None;
+    }
+    let relative_paths = paths
+        .iter()
+        .map(|path| path[parent.len()..].to_owned())
+        .collect();
+    Some((parent.to_owned(), relative_paths))
+}
+
+pub fn is_outside_root(relative_path: &str) -> bool {
+    relative_path == "/.." || relative_path.starts_with("/../")
+}
+
+/// Whether a character can't be part of a name. Control characters are allowed by some file
+/// systems but are rejected as well, as they are almost certainly a mistake.
+pub fn is_invalid_name_char(c: char) -> bool {
+    c.is_control() || (cfg!(windows) && matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*'))
+}
+
+/// Scans every opened folder, prefixing the paths with the label of their folder if there are
+/// several.
+pub fn get_entries_for_roots(roots: &Roots) -> Result<Vec<FileSystemEntry>, Error> {
+    let mut entries = Vec::new();
+    for (root, folder) in roots.folders.iter().enumerate() {
+        let prefix = roots.prefix(root);
+        for mut entry in get_entries_for_path(folder)? {
+            entry.root = root;
+            if !prefix.is_empty() {
+                entry.og_path.insert_str(0, &prefix);
+                entry.reset_path();
+            }
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
 }
 
 pub fn get_entries_for_path(initial_path: &str) -> Result<Vec<FileSystemEntry>, Error> {
@@ -60,17 +325,24 @@ impl<'a> PathFinder<'a> {
                             .expect("All paths found need to be under the initial path")
                             .to_owned();
                         let path = og_path.clone();
-                        let last_sep = path
-                            .char_indices()
-                            .rev()
-                            .find_map(|(i, c)| if c == '/' { Some(i) } else { None })
-                            .expect("There must be at least one separator");
+                        let last_sep = last_separator(&path);
+                        // An entry whose metadata can't be read, such as one deleted during the
+                        // scan, is still listed, it just sorts as if it was empty and undated.
+                        let metadata = de.metadata().ok();
+                        let size = match entry_type {
+                            EntryType::File => metadata.as_ref().map_or(0, |m| m.len()),
+                            EntryType::Folder => 0,
+                        };
                         let entry = FileSystemEntry {
                             og_path,
                             path,
                             last_sep,
                             depth,
                             entry_type,
+                            size,
+                            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+                            inode: metadata.as_ref().and_then(inode),
+                            root: 0,
                         };
                         self.entries.push(entry);
                     }
@@ -80,3 +352,122 @@ impl<'a> PathFinder<'a> {
         Ok(())
     }
 }
+
+#[cfg(unix)]
+pub fn inode(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+pub fn inode(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/a/b", "/a/b")]
+    #[case("a/b", "/a/b")]
+    #[case("//a/./b", "/a/b")]
+    #[case("/a/../b", "/b")]
+    #[case("/../a", "/../a")]
+    #[case("/a/../../b", "/../b")]
+    #[case("/a/", "/a/")]
+    #[case("/a/..", "/a/..")]
+    #[case("/a/.", "/a/.")]
+    fn test_normalize_relative_path(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(normalize_relative_path(path), expected);
+    }
+
+    #[rstest]
+    #[case(&["/home/u/a.jpg"], Some(("/home/u", vec!["/a.jpg"])))]
+    #[case(&["/home/u/a.jpg", "/home/u/b.jpg"], Some(("/home/u", vec!["/a.jpg", "/b.jpg"])))]
+    #[case(&["/home/u/x/a", "/home/u/y/b"], Some(("/home/u", vec!["/x/a", "/y/b"])))]
+    #[case(&["/home/u/ab/c", "/home/u/a"], Some(("/home/u", vec!["/ab/c", "/a"])))]
+    #[case(&["/home/a", "/tmp/b"], None)]
+    #[case(&[], None)]
+    fn test_common_parent(#[case] paths: &[&str], #[case] expected: Option<(&str, Vec<&str>)>) {
+        let paths = paths
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            common_parent(&paths),
+            expected.map(|(parent, relative_paths)| (
+                parent.to_owned(),
+                relative_paths.into_iter().map(str::to_owned).collect()
+            ))
+        );
+    }
+
+    #[rstest]
+    #[case("/home/u/photos", "/home/u/photos/a.jpg", "/a.jpg")]
+    #[case("/home/u/photos", "/home/u/photos/x/../a.jpg", "/a.jpg")]
+    #[case("/home/u/photos", "/home/u/other/a.jpg", "/../other/a.jpg")]
+    #[case("/home/u/photos", "/tmp/a.jpg", "/../../../tmp/a.jpg")]
+    #[case("/home/u/photos", "/home/u/photos", "/../photos")]
+    fn test_relative_to_root(#[case] root: &str, #[case] path: &str, #[case] expected: &str) {
+        assert_eq!(relative_to_root(root, path), expected);
+        assert_eq!(
+            is_outside_root(&relative_to_root(root, path)),
+            expected.starts_with("/..")
+        );
+    }
+
+    #[test]
+    fn test_roots() {
+        let roots = Roots::new([
+            "/home/u/Downloads/".to_owned(),
+            "/mnt/drive".to_owned(),
+            "/mnt/backup/Downloads".to_owned(),
+            "/mnt/drive".to_owned(),
+        ]);
+        assert_eq!(roots.folders.len(), 3);
+        assert_eq!(roots.labels, ["Downloads", "drive", "Downloads (2)"]);
+        assert_eq!(roots.prefix(2), "/Downloads (2)");
+
+        assert_eq!(roots.split("/drive/a/b.jpg"), Some((1, "/a/b.jpg")));
+        assert_eq!(roots.split("/Downloads (2)"), Some((2, "")));
+        assert_eq!(roots.split("/elsewhere/a"), None);
+        assert_eq!(
+            roots.absolute("/Downloads/a.jpg").as_deref(),
+            Some("/home/u/Downloads/a.jpg")
+        );
+        assert_eq!(roots.relative("/mnt/drive/x/a.jpg", 0), "/drive/x/a.jpg");
+        assert_eq!(
+            roots.entry_path("/mnt/drive/x/a.jpg").as_deref(),
+            Some("/drive/x/a.jpg")
+        );
+        assert_eq!(roots.entry_path("/mnt/drive"), None);
+        assert_eq!(roots.entry_path("/mnt/drive2/a.jpg"), None);
+        // Paths outside of every folder are made relative to the given one.
+        assert_eq!(roots.relative("/mnt/a.jpg", 1), "/drive/../a.jpg");
+        assert_eq!(
+            roots.absolute("/drive/../a.jpg").as_deref(),
+            Some("/mnt/a.jpg")
+        );
+        assert_eq!(roots.absolute("/a.jpg"), None);
+
+        assert_eq!(roots.normalize("drive/x/../../a.jpg"), "/drive/../a.jpg");
+        assert_eq!(
+            roots.normalize("/drive/../Downloads/a.jpg"),
+            "/drive/../Downloads/a.jpg"
+        );
+        assert_eq!(roots.normalize("/elsewhere/../a.jpg"), "/a.jpg");
+
+        let root = Roots::new(["/home/u/photos".to_owned()]);
+        assert_eq!(root.prefix(0), "");
+        assert_eq!(
+            root.absolute("/../other/a.jpg").as_deref(),
+            Some("/home/u/other/a.jpg")
+        );
+        assert_eq!(root.relative("/home/u/other/a.jpg", 0), "/../other/a.jpg");
+        assert_eq!(root.normalize("/photos/../a.jpg"), "/a.jpg");
+        assert_eq!(Roots::default().absolute("/a"), None);
+    }
+}
@@ -0,0 +1,294 @@
+use std::{collections::BTreeSet, io, process::Command};
+
+use crate::fs::Roots;
+
+/// Runs git in `folder` and returns what it printed, or what it complained about as an error.
+/// Pathspecs are taken literally rather than as patterns.
+fn git(folder: &str, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("--literal-pathspecs")
+        .arg("-C")
+        .arg(folder)
+        .args(args)
+        .output()?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr);
+        Err(io::Error::other(format!("git: {}", message.trim())))
+    }
+}
+
+/// An opened folder that is in a git working tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    /// The folder as it was opened, which the paths of the entries start with.
+    folder: String,
+    /// The folder with all symlinks resolved, as git only uses such paths.
+    canonical_folder: String,
+    working_tree: String,
+}
+
+impl Repository {
+    /// The repositories of all opened folders that are in a git working tree.
+    pub fn detect(roots: &Roots) -> Vec<Self> {
+        roots
+            .folders
+            .iter()
+            .filter_map(|folder| {
+                let canonical_folder = std::fs::canonicalize(folder).ok()?.to_str()?.to_owned();
+                let output = git(&canonical_folder, &["rev-parse", "--show-toplevel"]).ok()?;
+                let working_tree = String::from_utf8(output).ok()?;
+                Some(Self {
+                    folder: folder.clone(),
+                    canonical_folder,
+                    working_tree: working_tree.trim_end_matches('\n').to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Turns an absolute path in the opened folder into a path relative to the working tree.
+    fn tree_path(&self, path: &str) -> Option<String> {
+        let rest = strip_folder(path, &self.folder)?;
+        let canonical_path = format!("{}{rest}", self.canonical_folder);
+        strip_folder(&canonical_path, &self.working_tree)?
+            .strip_prefix('/')
+            .map(str::to_owned)
+    }
+
+    /// Turns a path relative to the working tree into an absolute path in the opened folder.
+    fn folder_path(&self, tree_path: &str) -> Option<String> {
+        let canonical_path = format!("{}/{tree_path}", self.working_tree);
+        let rest = strip_folder(&canonical_path, &self.canonical_folder)?;
+        Some(format!("{}{rest}", self.folder))
+    }
+}
+
+/// What is left of `path` after the `folder` it is in or is, starting with a separator if
+/// anything is left.
+fn strip_folder<'a>(path: &'a str, folder: &str) -> Option<&'a str> {
+    path.strip_prefix(folder)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Runs git once for every working tree, limited to the opened folders in it. Returns the
+/// repositories of each working tree along with what git printed.
+fn run_per_working_tree<'a>(
+    repositories: &'a [Repository],
+    args: &[&str],
+) -> io::Result<Vec<(Vec<&'a Repository>, Vec<u8>)>> {
+    let mut outputs = Vec::new();
+    for (i, repository) in repositories.iter().enumerate() {
+        // Several opened folders can be in the same working tree.
+        if repositories[..i]
+            .iter()
+            .any(|other| other.working_tree == repository.working_tree)
+        {
+            continue;
+        }
+        let same_tree = repositories
+            .iter()
+            .filter(|other| other.working_tree == repository.working_tree)
+            .collect::<Vec<_>>();
+        let pathspecs = same_tree
+            .iter()
+            .map(|other| {
+                strip_folder(&other.canonical_folder, &other.working_tree)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .unwrap_or(".")
+            })
+            .collect::<Vec<_>>();
+        let mut args = args.to_vec();
+        args.push("--");
+        args.extend(pathspecs);
+        let output = git(&repository.working_tree, &args)?;
+        outputs.push((same_tree, output));
+    }
+    Ok(outputs)
+}
+
+/// Whether `path` is one of `paths` or inside one of them.
+fn is_in_any(path: &str, paths: &BTreeSet<&str>) -> bool {
+    paths.contains(path)
+        || path
+            .match_indices('/')
+            .any(|(i, _)| paths.contains(&path[..i]))
+}
+
+/// The tracked files among `paths` and in the folders among them that have changes that aren't
+/// committed yet, as absolute paths in the opened folders.
+pub fn uncommitted(repositories: &[Repository], paths: &[&str]) -> io::Result<Vec<String>> {
+    let paths = paths.iter().copied().collect::<BTreeSet<_>>();
+    let args = ["status", "--porcelain", "-z", "--untracked-files=no"];
+    let mut files = Vec::new();
+    for (same_tree, output) in run_per_working_tree(repositories, &args)? {
+        let mut records = output.split(|&byte| byte == 0);
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+            let tree_path = String::from_utf8_lossy(&record[3..]);
+            files.extend(
+                same_tree
+                    .iter()
+                    .find_map(|repository| repository.folder_path(&tree_path))
+                    .filter(|path| is_in_any(path, &paths)),
+            );
+            // Renames and copies are followed by the path they were made from.
+            if record[..2].contains(&b'R') || record[..2].contains(&b'C') {
+                records.next();
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The files git tracks among the entries that are about to be moved, kept up to date as they
+/// are moved. Without repositories nothing is tracked.
+#[derive(Debug, Default)]
+pub struct Tracked {
+    repositories: Vec<Repository>,
+    /// Absolute paths in the opened folders.
+    files: BTreeSet<String>,
+}
+
+impl Tracked {
+    /// Asks git once per working tree which files are tracked among `paths` and in the folders
+    /// among them.
+    pub fn find(repositories: Vec<Repository>, paths: &[&str]) -> io::Result<Self> {
+        let paths = paths.iter().copied().collect::<BTreeSet<_>>();
+        let mut files = BTreeSet::new();
+        for (same_tree, output) in run_per_working_tree(&repositories, &["ls-files", "-z"])? {
+            for tree_path in output
+                .split(|&byte| byte == 0)
+                .filter(|path| !path.is_empty())
+            {
+                let tree_path = String::from_utf8_lossy(tree_path);
+                files.extend(
+                    same_tree
+                        .iter()
+                        .find_map(|repository| repository.folder_path(&tree_path))
+                        .filter(|path| is_in_any(path, &paths)),
+                );
+            }
+        }
+        Ok(Self {
+            repositories,
+            files,
+        })
+    }
+
+    /// Whether git tracks `path`, or anything inside of it if it's a folder.
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains(path) || self.inside(path).next().is_some()
+    }
+
+    fn inside<'a>(&'a self, folder: &str) -> impl Iterator<Item = &'a String> {
+        self.files.range(format!("{folder}/")..format!("{folder}0"))
+    }
+
+    /// Moves `from` to `to` with `git mv` if git tracks it and both are in the same working
+    /// tree, so that git sees a rename rather than a deleted and an untracked file. Returns
+    /// whether it did.
+    pub fn mv(&mut self, from: &str, to: &str) -> io::Result<bool> {
+        if !self.contains(from) {
+            return Ok(false);
+        }
+        let tree_paths = self.repositories.iter().find_map(|repository| {
+            let from = repository.tree_path(from)?;
+            let to = self
+                .repositories
+                .iter()
+                .filter(|other| other.working_tree == repository.working_tree)
+                .find_map(|other| other.tree_path(to))?;
+            Some((&repository.working_tree, from, to))
+        });
+        let Some((working_tree, tree_from, tree_to)) = tree_paths else {
+            return Ok(false);
+        };
+        git(working_tree, &["mv", "--", &tree_from, &tree_to])?;
+
+        let moved = self
+            .inside(from)
+            .cloned()
+            .chain(self.files.get(from).cloned())
+            .collect::<Vec<_>>();
+        for path in moved {
+            self.files.remove(&path);
+            self.files.insert(format!("{to}{}", &path[from.len()..]));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git() {
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("mareto-git-{}", std::process::id()));
+        let repository = dir.join("repository");
+        std::fs::create_dir_all(repository.join("folder")).unwrap();
+        std::fs::write(repository.join("folder").join("a.txt"), "a").unwrap();
+        std::fs::write(repository.join("b [1].txt"), "b").unwrap();
+        std::fs::write(repository.join("untracked.txt"), "c").unwrap();
+        let repository = repository.to_str().unwrap().to_owned();
+
+        assert_eq!(Repository::detect(&Roots::new([repository.clone()])), []);
+        git(&repository, &["init", "-q"]).unwrap();
+        git(&repository, &["add", "folder", "b [1].txt"]).unwrap();
+        git(
+            &repository,
+            &[
+                "-c",
+                "user.name=mareto",
+                "-c",
+                "user.email=mareto@example.com",
+                "commit",
+                "-qm",
+                "initial",
+            ],
+        )
+        .unwrap();
+
+        // The folder is opened through a symlink, so its path differs from the one git reports.
+        #[cfg(unix)]
+        let root = {
+            let link = dir.join("link");
+            std::os::unix::fs::symlink(&repository, &link).unwrap();
+            link.to_str().unwrap().to_owned()
+        };
+        #[cfg(not(unix))]
+        let root = repository.clone();
+        let repositories = Repository::detect(&Roots::new([root.clone()]));
+        assert_eq!(repositories.len(), 1);
+
+        let path = |name: &str| format!("{root}/{name}");
+        let (folder, b, untracked) = (path("folder"), path("b [1].txt"), path("untracked.txt"));
+        std::fs::write(&b, "changed").unwrap();
+        assert_eq!(
+            uncommitted(&repositories, &[&folder, &b, &untracked]).unwrap(),
+            std::slice::from_ref(&b)
+        );
+
+        let mut tracked = Tracked::find(repositories, &[&folder, &b, &untracked]).unwrap();
+        assert!(tracked.contains(&folder));
+        assert!(!tracked.contains(&untracked));
+        assert!(!tracked.mv(&untracked, &path("c.txt")).unwrap());
+
+        assert!(tracked.mv(&folder, &path("renamed")).unwrap());
+        assert!(tracked.contains(&path("renamed/a.txt")));
+        assert!(tracked.mv(&path("renamed/a.txt"), &path("a.txt")).unwrap());
+        let status = git(&repository, &["status", "--porcelain"]).unwrap();
+        assert!(String::from_utf8(status)
+            .unwrap()
+            .contains("R  folder/a.txt -> a.txt"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,40 @@
+mod apply;
 mod bit_set;
+mod cli;
+mod config;
+mod diff;
 mod fs;
+mod git;
+mod mapping;
 mod mareto;
+mod presets;
+mod session;
+mod shortcuts;
 mod state;
 mod ui;
+mod watcher;
 
 use iced::{Application, Settings, Size};
 
-use crate::mareto::Mareto;
+use crate::{
+    cli::{Flags, USAGE},
+    mareto::Mareto,
+};
 
 fn main() -> iced::Result {
+    let flags = match Flags::parse(std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
     Mareto::run(Settings {
+        flags,
         window: iced::window::Settings {
             min_size: Some(Size::new(1280.0, 720.0)),
+            // The config is saved before the window is closed.
+            exit_on_close_request: false,
             ..Default::default()
         },
         ..Default::default()
@@ -0,0 +1,493 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apply::{Operation, Plan},
+    fs::{FileSystemEntry, Roots},
+    mareto::Error,
+};
+
+/// The file formats a mapping can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Json,
+}
+
+impl Format {
+    pub const EXTENSIONS: [&'static str; 4] = ["csv", "tsv", "tab", "json"];
+
+    /// Picks the format from the extension of a file, falling back to CSV.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("tsv" | "tab") => Self::Tsv,
+            Some("json") => Self::Json,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// The first fields of a header row, which is skipped when a CSV or TSV file starts with one.
+const HEADERS: [&str; 7] = [
+    "old", "old path", "old_path", "from", "source", "original", "path",
+];
+
+/// A mapping in a JSON file, either an object of old paths to new paths, a list of pairs or a
+/// list of objects with an old and a new path.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonMapping {
+    Object(BTreeMap<String, String>),
+    Pairs(Vec<(String, String)>),
+    Records(Vec<JsonRecord>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    #[serde(alias = "from")]
+    old: String,
+    #[serde(alias = "to")]
+    new: String,
+}
+
+/// Reads the rows of a mapping, each an old path and the new path it should get.
+pub fn load(path: &Path) -> Result<Vec<(String, String)>, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    parse(&contents, Format::from_path(path))
+}
+
+fn parse(contents: &str, format: Format) -> Result<Vec<(String, String)>, Error> {
+    match format {
+        Format::Csv => parse_delimited(contents, b','),
+        Format::Tsv => parse_delimited(contents, b'\t'),
+        Format::Json => match serde_json::from_str(contents) {
+            Ok(JsonMapping::Object(rows)) => Ok(rows.into_iter().collect()),
+            Ok(JsonMapping::Pairs(rows)) => Ok(rows),
+            Ok(JsonMapping::Records(rows)) => {
+                Ok(rows.into_iter().map(|row| (row.old, row.new)).collect())
+            }
+            Err(_) => Err(Error::Mapping(
+                "expected an object of old paths to new paths, a list of pairs or a list of \
+                 objects with an \"old\" and a \"new\" path"
+                    .to_owned(),
+            )),
+        },
+    }
+}
+
+fn parse_delimited(contents: &str, delimiter: u8) -> Result<Vec<(String, String)>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|error| Error::Mapping(error.to_string()))?;
+        if record.iter().all(str::is_empty)
+            || (i == 0 && HEADERS.contains(&record[0].to_ascii_lowercase().as_str()))
+        {
+            continue;
+        }
+        match (record.get(0), record.get(1)) {
+            (Some(old), Some(new)) if !old.is_empty() && !new.is_empty() => {
+                rows.push((old.to_owned(), new.to_owned()));
+            }
+            _ => {
+                let line = record.position().map_or(i as u64 + 1, |p| p.line());
+                return Err(Error::Mapping(format!(
+                    "line {line} doesn't have an old and a new path"
+                )));
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// The file extensions a plan can be exported as.
+pub const EXPORT_EXTENSIONS: [&str; 4] = ["csv", "tsv", "json", "sh"];
+
+/// Writes a plan as a shell script of its operations if the file ends with `.sh`, and as a
+/// mapping of where every entry ends up otherwise.
+pub fn export(plan: &Plan, path: &Path) -> Result<(), Error> {
+    let is_script = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("sh"));
+    let contents = if is_script {
+        to_shell_script(plan)
+    } else {
+        to_mapping(plan, Format::from_path(path))
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// The original and final absolute path of every renamed entry, leaving out the temporary names
+/// used to break cycles, as a mapping that can be imported again.
+fn to_mapping(plan: &Plan, format: Format) -> String {
+    let renames = plan
+        .sources
+        .iter()
+        .map(|source| (source.path.as_str(), source.target.as_str()));
+    match format {
+        Format::Csv | Format::Tsv => {
+            let delimiter = if format == Format::Tsv { b'\t' } else { b',' };
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(Vec::new());
+            let written = std::iter::once(("old", "new"))
+                .chain(renames)
+                .try_for_each(|(old, new)| writer.write_record([old, new]));
+            written.expect("Writing to memory doesn't fail");
+            let bytes = writer.into_inner().expect("Writing to memory doesn't fail");
+            String::from_utf8(bytes).expect("All paths are valid UTF-8")
+        }
+        Format::Json => {
+            let records = renames
+                .map(|(old, new)| JsonRecord {
+                    old: old.to_owned(),
+                    new: new.to_owned(),
+                })
+                .collect::<Vec<_>>();
+            let mut json = serde_json::to_string_pretty(&records)
+                .expect("A list of paths can always be serialized");
+            json.push('\n');
+            json
+        }
+    }
+}
+
+/// The operations of a plan in order as a POSIX shell script that stops at the first one that
+/// fails. As `mv -n` succeeds without doing anything if the target exists, every move checks for
+/// that first and stops the script instead of running the rest against the wrong entries.
+fn to_shell_script(plan: &Plan) -> String {
+    let mut script = String::from("#!/bin/sh\nset -e\n");
+    for operation in &plan.operations {
+        match operation {
+            Operation::CreateFolder(path) => {
+                script.push_str(&format!("mkdir -p -- {}\n", shell_quote(path)));
+            }
+            Operation::Rename { from, to } => {
+                let (from, to) = (shell_quote(from), shell_quote(to));
+                script.push_str(&format!(
+                    "if [ -e {to} ] || [ -L {to} ]; then \
+                     printf '%s already exists\\n' {to} >&2; exit 1; fi\n\
+                     mv -n -- {from} {to}\n"
+                ));
+            }
+        }
+    }
+    script
+}
+
+/// Quotes a string so that the shell passes it on as a single argument, as is.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// The rows of a mapping matched against the entries of the opened folders.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Matches {
+    /// The new path of every matched entry, keyed by its original path.
+    pub edits: BTreeMap<String, String>,
+    /// Rows whose old path doesn't belong to any entry.
+    pub unmatched: Vec<(String, String)>,
+    /// Rows whose old path belongs to an entry both as an absolute and as a relative path, or
+    /// that give an entry a different new path than another row.
+    pub ambiguous: Vec<(String, String)>,
+    /// Rows whose new path is an absolute path outside of the opened folders.
+    pub outside: Vec<(String, String)>,
+}
+
+/// Matches the rows of a mapping against the original paths of the entries.
+///
+/// Paths can be absolute, or relative to the opened folders like the paths in the editor. A row
+/// with an absolute old path needs a new path in one of the opened folders rather than one that
+/// is only relative to them, and a new path without a separator renames the entry in its current
+/// folder.
+pub fn resolve(roots: &Roots, entries: &[FileSystemEntry], rows: Vec<(String, String)>) -> Matches {
+    let og_paths = entries
+        .iter()
+        .map(|entry| entry.og_path.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut matches = Matches::default();
+    let mut matched: BTreeMap<&str, Vec<(String, String)>> = BTreeMap::new();
+    for (old, new) in rows {
+        let absolute = roots.entry_path(&old);
+        let relative = relative_entry_path(roots, &old);
+        let candidates = [(absolute, true), (Some(relative), false)]
+            .into_iter()
+            .filter_map(|(path, is_absolute)| {
+                let og_path = og_paths.get(path?.as_str())?;
+                Some((*og_path, is_absolute))
+            })
+            .collect::<Vec<_>>();
+        let (og_path, is_absolute) = match candidates[..] {
+            [] => {
+                matches.unmatched.push((old, new));
+                continue;
+            }
+            [candidate] => candidate,
+            _ => {
+                matches.ambiguous.push((old, new));
+                continue;
+            }
+        };
+        if !new.contains('/') || !is_absolute || roots.entry_path(&new).is_some() {
+            matched.entry(og_path).or_default().push((old, new));
+        } else {
+            matches.outside.push((old, new));
+        }
+    }
+
+    for (og_path, rows) in matched {
+        let mut paths = rows
+            .iter()
+            .map(|(_, new)| new_path(roots, og_path, new))
+            .collect::<Vec<_>>();
+        paths.dedup();
+        if let [path] = &paths[..] {
+            matches.edits.insert(og_path.to_owned(), path.clone());
+        } else {
+            matches.ambiguous.extend(rows);
+        }
+    }
+    matches
+}
+
+/// The path of the entry a path relative to the opened folders points to.
+fn relative_entry_path(roots: &Roots, path: &str) -> String {
+    roots.normalize(path)
+}
+
+/// The new path of the entry at `og_path`, given the new path of the row that matched it.
+fn new_path(roots: &Roots, og_path: &str, new: &str) -> String {
+    if new.contains('/') {
+        roots
+            .entry_path(new)
+            .unwrap_or_else(|| relative_entry_path(roots, new))
+    } else {
+        let (folder, _) = og_path
+            .rsplit_once('/')
+            .expect("All paths contain a separator");
+        format!("{folder}/{new}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    use crate::{apply::Source, fs::EntryType};
+
+    fn entry(og_path: &str) -> FileSystemEntry {
+        FileSystemEntry::for_test(og_path, og_path, EntryType::File)
+    }
+
+    fn rows(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(Format::Csv, "old,new\n/a.jpg, /b.jpg\n\n\"/c,d.jpg\",/e.jpg\n")]
+    #[case(Format::Tsv, "/a.jpg\t/b.jpg\n/c,d.jpg\t/e.jpg\n")]
+    #[case(Format::Json, r#"{"/a.jpg": "/b.jpg", "/c,d.jpg": "/e.jpg"}"#)]
+    #[case(Format::Json, r#"[["/a.jpg", "/b.jpg"], ["/c,d.jpg", "/e.jpg"]]"#)]
+    #[case(
+        Format::Json,
+        r#"[{"old": "/a.jpg", "new": "/b.jpg"}, {"from": "/c,d.jpg", "to": "/e.jpg"}]"#
+    )]
+    fn test_parse(#[case] format: Format, #[case] contents: &str) {
+        assert_eq!(
+            parse(contents, format).unwrap(),
+            rows(&[("/a.jpg", "/b.jpg"), ("/c,d.jpg", "/e.jpg")])
+        );
+    }
+
+    #[rstest]
+    #[case(Format::Csv, "/a.jpg,/b.jpg\n/c.jpg\n")]
+    #[case(Format::Csv, "/a.jpg,\n")]
+    #[case(Format::Json, r#"{"/a.jpg": 1}"#)]
+    fn test_parse_invalid(#[case] format: Format, #[case] contents: &str) {
+        assert!(parse(contents, format).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let roots = Roots::new(["/home/photos".to_owned()]);
+        let entries = [
+            entry("/a/IMG_1.jpg"),
+            entry("/a/IMG_2.jpg"),
+            entry("/b/IMG_2.jpg"),
+            entry("/b/IMG_3.jpg"),
+            entry("/b/IMG_4.jpg"),
+            entry("/IMG_5.jpg"),
+            entry("/home/photos/IMG_6.jpg"),
+            entry("/IMG_6.jpg"),
+        ];
+        let matches = resolve(
+            &roots,
+            &entries,
+            rows(&[
+                ("/home/photos/a/IMG_1.jpg", "holiday_1.jpg"),
+                ("IMG_2.jpg", "holiday_2.jpg"),
+                ("IMG_5.jpg", "holiday_5.jpg"),
+                ("b/IMG_3.jpg", "/c/IMG_3.jpg"),
+                ("/b/IMG_4.jpg", "/home/photos/b/4.jpg"),
+                ("/b/IMG_3.jpg", "/c/IMG_3.jpg"),
+                ("/b/missing.jpg", "/b/found.jpg"),
+                ("/a/IMG_2.jpg", "/a/first.jpg"),
+                ("/a/IMG_2.jpg", "/a/second.jpg"),
+                ("/home/photos/IMG_6.jpg", "/home/photos/6.jpg"),
+                ("/home/photos/b/IMG_2.jpg", "/mnt/archive/IMG_2.jpg"),
+            ]),
+        );
+
+        assert_eq!(
+            matches.edits,
+            BTreeMap::from([
+                ("/a/IMG_1.jpg".to_owned(), "/a/holiday_1.jpg".to_owned()),
+                ("/IMG_5.jpg".to_owned(), "/holiday_5.jpg".to_owned()),
+                ("/b/IMG_3.jpg".to_owned(), "/c/IMG_3.jpg".to_owned()),
+                ("/b/IMG_4.jpg".to_owned(), "/b/4.jpg".to_owned()),
+            ])
+        );
+        assert_eq!(
+            matches.unmatched,
+            rows(&[
+                ("IMG_2.jpg", "holiday_2.jpg"),
+                ("/b/missing.jpg", "/b/found.jpg")
+            ])
+        );
+        assert_eq!(
+            matches.ambiguous,
+            rows(&[
+                ("/home/photos/IMG_6.jpg", "/home/photos/6.jpg"),
+                ("/a/IMG_2.jpg", "/a/first.jpg"),
+                ("/a/IMG_2.jpg", "/a/second.jpg"),
+            ])
+        );
+        assert_eq!(
+            matches.outside,
+            rows(&[("/home/photos/b/IMG_2.jpg", "/mnt/archive/IMG_2.jpg")])
+        );
+    }
+
+    fn plan() -> Plan {
+        let source = |path: &str, target: &str| Source {
+            path: path.to_owned(),
+            target: target.to_owned(),
+            entry_type: EntryType::File,
+            size: 0,
+            modified: None,
+            inode: None,
+        };
+        Plan {
+            operations: vec![
+                Operation::CreateFolder("/p/new folder".to_owned()),
+                Operation::Rename {
+                    from: "/p/a".to_owned(),
+                    to: "/p/.mareto-1-a".to_owned(),
+                },
+                Operation::Rename {
+                    from: "/p/it's".to_owned(),
+                    to: "/p/a".to_owned(),
+                },
+                Operation::Rename {
+                    from: "/p/.mareto-1-a".to_owned(),
+                    to: "/p/new folder/a, \"b\"".to_owned(),
+                },
+            ],
+            sources: vec![
+                source("/p/a", "/p/new folder/a, \"b\""),
+                source("/p/it's", "/p/a"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_export_mapping() {
+        let expected = rows(&[("/p/a", "/p/new folder/a, \"b\""), ("/p/it's", "/p/a")]);
+        for format in [Format::Csv, Format::Tsv, Format::Json] {
+            assert_eq!(
+                parse(&to_mapping(&plan(), format), format).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_export_shell_script() {
+        let check = |path: &str| {
+            format!(
+                "if [ -e {path} ] || [ -L {path} ]; then \
+                 printf '%s already exists\\n' {path} >&2; exit 1; fi\n"
+            )
+        };
+        assert_eq!(
+            to_shell_script(&plan()),
+            [
+                "#!/bin/sh\nset -e\nmkdir -p -- '/p/new folder'\n".to_owned(),
+                check("'/p/.mareto-1-a'"),
+                "mv -n -- '/p/a' '/p/.mareto-1-a'\n".to_owned(),
+                check("'/p/a'"),
+                "mv -n -- '/p/it'\\''s' '/p/a'\n".to_owned(),
+                check("'/p/new folder/a, \"b\"'"),
+                "mv -n -- '/p/.mareto-1-a' '/p/new folder/a, \"b\"'\n".to_owned(),
+            ]
+            .concat()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_script_stops_at_existing_target() {
+        let dir = std::env::temp_dir().join(format!("mareto-script-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| format!("{}/{name}", dir.to_str().unwrap());
+        for name in ["a", "b", "c"] {
+            std::fs::write(path(name), name).unwrap();
+        }
+        let plan = Plan {
+            operations: vec![
+                Operation::Rename {
+                    from: path("a"),
+                    to: path("b"),
+                },
+                Operation::Rename {
+                    from: path("c"),
+                    to: path("a"),
+                },
+            ],
+            sources: Vec::new(),
+        };
+        let script = path("plan.sh");
+        std::fs::write(&script, to_shell_script(&plan)).unwrap();
+
+        let output = std::process::Command::new("sh")
+            .arg(&script)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(std::fs::read_to_string(path("a")).unwrap(), "a");
+        assert_eq!(std::fs::read_to_string(path("b")).unwrap(), "b");
+        assert_eq!(std::fs::read_to_string(path("c")).unwrap(), "c");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
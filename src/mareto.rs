@@ -1,13 +1,28 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use iced::{
-    executor,
-    widget::{column, container, row, rule::Rule, text, text_editor},
-    Application, Command, Element, Theme,
+    event, executor, keyboard,
+    widget::{column, container, row, rule::Rule, scrollable, text, text_editor, text_input},
+    window, Application, Command, Element, Event, Size, Subscription, Theme,
 };
 
 use crate::{
-    fs::get_entries_for_path,
-    state::{EditorState, FilterOptions},
-    ui,
+    apply::{self, ApplyError, Plan, PlanError, SourceChange},
+    cli::Flags,
+    config::Config,
+    fs::{common_parent, get_entries_for_roots, Roots},
+    git::{self, Repository, Tracked},
+    mapping,
+    presets::{Preset, Presets, PresetsState},
+    session::Session,
+    shortcuts::{self, Shortcuts},
+    state::{CursorAction, EditorState, FilterJobResult, FilterKind, FilterOptions},
+    ui, watcher,
 };
 use crate::{
     fs::FileSystemEntry,
@@ -17,12 +32,126 @@ use crate::{
 #[derive(Debug, Clone)]
 pub enum Error {
     DialogClosed,
-    IOError(std::io::ErrorKind),
+    Io(std::io::ErrorKind),
+    Plan(Vec<PlanError>),
+    Apply(ApplyError),
+    /// Entries that changed on disk since they were scanned, so nothing was applied.
+    SourcesChanged(Vec<SourceChange>),
+    Config(String),
+    /// The config file could not be written when closing, so the window was kept open.
+    ConfigNotSaved(String),
+    /// Shortcut actions in the config file that don't exist, which are ignored.
+    UnknownShortcutActions(Vec<String>),
+    Watch(String),
+    Presets(String),
+    Session(String),
+    Mapping(String),
+    /// Rows of an imported mapping that don't match exactly one entry.
+    UnmatchedRows {
+        unmatched: Vec<(String, String)>,
+        ambiguous: Vec<(String, String)>,
+        outside: Vec<(String, String)>,
+    },
+    /// Pending edits whose original path no longer exists after opening the folders again, or
+    /// whose new path is not one the editor could have made.
+    UnmatchedEdits {
+        unmatched: Vec<(String, String)>,
+        invalid: Vec<(String, String)>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::DialogClosed => write!(f, "The dialog was closed"),
+            Error::Io(kind) => write!(f, "{kind}"),
+            Error::Plan(errors) => {
+                writeln!(f, "The changes cannot be applied:")?;
+                errors.iter().try_for_each(|error| writeln!(f, "{error}"))
+            }
+            Error::Apply(error) => write!(f, "{error}"),
+            Error::SourcesChanged(changes) => {
+                writeln!(
+                    f,
+                    "Nothing was applied, as these entries changed since they were scanned. \
+                     Refresh to see their current state:"
+                )?;
+                changes
+                    .iter()
+                    .try_for_each(|change| writeln!(f, "{change}"))
+            }
+            Error::Config(error) => write!(
+                f,
+                "The config file could not be read, so the defaults are used and it is left \
+                 untouched: {error}"
+            ),
+            Error::ConfigNotSaved(error) => write!(
+                f,
+                "The config file could not be saved. Close the window again to quit without \
+                 saving it: {error}"
+            ),
+            Error::UnknownShortcutActions(names) => write!(
+                f,
+                "The config file has shortcuts for unknown actions, which are ignored: {}",
+                names.join(", ")
+            ),
+            Error::Presets(error) => write!(f, "{error}"),
+            Error::Watch(error) => write!(f, "The folders can't be watched for changes: {error}"),
+            Error::Session(error) => write!(f, "The session file could not be read: {error}"),
+            Error::Mapping(error) => write!(f, "The mapping could not be read: {error}"),
+            Error::UnmatchedRows {
+                unmatched,
+                ambiguous,
+                outside,
+            } => {
+                writeln!(
+                    f,
+                    "{} rows of the mapping were not imported:",
+                    unmatched.len() + ambiguous.len() + outside.len()
+                )?;
+                unmatched.iter().try_for_each(|(old, new)| {
+                    writeln!(f, "{old} -> {new}: no entry has this path")
+                })?;
+                ambiguous.iter().try_for_each(|(old, new)| {
+                    writeln!(
+                        f,
+                        "{old} -> {new}: it matches an entry both as an absolute and as a \
+                         relative path, or another row renames the same entry differently"
+                    )
+                })?;
+                outside.iter().try_for_each(|(old, new)| {
+                    writeln!(
+                        f,
+                        "{old} -> {new}: the new path is outside of the opened folders"
+                    )
+                })
+            }
+            Error::UnmatchedEdits { unmatched, invalid } => {
+                writeln!(
+                    f,
+                    "{} pending edits were dropped:",
+                    unmatched.len() + invalid.len()
+                )?;
+                unmatched.iter().try_for_each(|(og_path, path)| {
+                    writeln!(f, "{og_path} -> {path}: no entry has this path anymore")
+                })?;
+                invalid.iter().try_for_each(|(og_path, path)| {
+                    writeln!(f, "{og_path} -> {path}: the new path is not a valid path")
+                })
+            }
+        }
+    }
+}
+
+impl From<ApplyError> for Error {
+    fn from(value: ApplyError) -> Self {
+        Self::Apply(value)
+    }
 }
 
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
-        Self::IOError(value.kind())
+        Self::Io(value.kind())
     }
 }
 
@@ -30,9 +159,41 @@ impl From<std::io::Error> for Error {
 pub enum Message {
     // Top-level actions
     OpenFolder,
-    FolderSelected(Result<(String, Vec<FileSystemEntry>), Error>),
+    AddFolder,
+    FolderAdded(Result<(Roots, Vec<FileSystemEntry>), Error>),
+    RecentFolderSelected(String),
+    FolderSelected(Result<(Roots, Vec<FileSystemEntry>), Error>),
     ApplyChanges,
-    //ApplyOutcome(Result<(), Error>),
+    ApplyOutcome(Result<(Roots, Vec<FileSystemEntry>), Error>),
+    Refresh,
+    FoldersChanged,
+    /// A rescan, along with how many times changes had been applied when it was started.
+    Refreshed(usize, Result<(Roots, Vec<FileSystemEntry>), Error>),
+    WatchFailed(Error),
+    SaveSession,
+    SessionSaved(Result<(), Error>),
+    OpenSession,
+    SessionOpened(Result<(Session, Roots, Vec<FileSystemEntry>), Error>),
+    ImportMapping,
+    MappingImported(Result<Vec<(String, String)>, Error>),
+    ExportPlan,
+    PlanExported(Result<(), Error>),
+    KeyPressed(keyboard::Key, keyboard::Modifiers, event::Status),
+    WindowResized(Size),
+    FileDropped(PathBuf),
+    /// Sent a little while after a path was dropped, with the amount of paths dropped by then.
+    DropFinished(usize),
+    /// The folder of a drop was opened. Unless a single folder was dropped, the paths of the
+    /// dropped entries are the only ones to show.
+    DroppedPathsLoaded(
+        Option<Vec<String>>,
+        Result<(Roots, Vec<FileSystemEntry>), Error>,
+    ),
+    DroppedEntriesCleared,
+    CloseRequested,
+    FocusFilter,
+    FocusFind,
+    FocusReplace,
 
     // Options updates
     FilterUpdated(String),
@@ -45,13 +206,45 @@ pub enum Message {
     ShowFilesToggled(bool),
     ShowFoldersToggled(bool),
     SortOrderSelected(SortingOption),
+    SecondarySortOrderSelected(SortingOption),
+    DirectoriesFirstToggled(bool),
     DisplayTypeSelected(DisplayType),
+    ShowOnlyChangedToggled(bool),
     RemoveFoldersToggled(bool),
     PreviewChangesToggled(bool),
+    AllowOutsideRootToggled(bool),
+    WatchFoldersToggled(bool),
+    UseGitToggled(bool),
+    RepositoriesDetected(Roots, Vec<Repository>),
     ThemeSelected(Theme),
+    FiltersUpdated(FilterJobResult),
+
+    // Presets
+    PresetNameUpdated(String),
+    PresetSelected(String),
+    SavePreset,
+    LoadPreset,
+    RenamePreset,
+    DeletePreset,
 
     // Editor actions
     EditAction(text_editor::Action),
+    CursorAction(CursorAction),
+    ScrollEditorTo(scrollable::AbsoluteOffset),
+    ResetCurrentEntry,
+    ResetAllEntries,
+    Undo,
+    Redo,
+
+    // Find and replace
+    FindUpdated(String),
+    FindCaseSensitivityToggled,
+    FindRegexToggled,
+    ReplaceUpdated(String),
+    FindNext,
+    FindPrevious,
+    ReplaceCurrent,
+    ReplaceAll,
 }
 
 #[derive(Debug, Default)]
@@ -59,17 +252,83 @@ pub struct Mareto {
     filters: FilterOptions,
     options: Options,
     editor_state: EditorState,
+    shortcuts: Shortcuts,
+    config: Config,
+    /// Whether the config is saved on close, which is not the case if it couldn't be read.
+    save_config: bool,
+    presets: PresetsState,
+    /// The paths of a drop that is still arriving, as every dropped path is its own event.
+    dropped_paths: Vec<PathBuf>,
+    /// The git repositories the opened folders are in, only looked for while tracked files are
+    /// renamed with git.
+    repositories: Vec<Repository>,
+    /// Whether changes are being applied, during which the folders aren't watched or rescanned.
+    applying: bool,
+    /// How many times changes were applied, so that rescans started before the last time are
+    /// dropped.
+    applied_count: usize,
+    error: Option<Error>,
 }
 
 impl Application for Mareto {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
-        let mareto = Self::default();
-        (mareto, Command::none())
+    fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+        let mut mareto = Self::default();
+        let mut command = Command::none();
+        match Presets::load() {
+            Ok(presets) => {
+                mareto.presets.presets = presets;
+                mareto.presets.is_loaded = true;
+            }
+            Err(error) => mareto.error = Some(error),
+        }
+        match Config::load() {
+            Ok(config) => {
+                config.apply(&mut mareto.options, &mut mareto.filters);
+                let unknown = mareto.shortcuts.remap(&config.shortcuts);
+                if !unknown.is_empty() {
+                    mareto.error = Some(Error::UnknownShortcutActions(unknown));
+                }
+                if let Some(window) = config.window {
+                    command = window::resize(window::Id::MAIN, window.into());
+                }
+                mareto.config = config;
+                mareto.save_config = true;
+            }
+            Err(error) => mareto.error = Some(error),
+        }
+        if !flags.folders.is_empty() {
+            // The folders are made absolute, as paths are shown and compared relative to them.
+            let roots = Roots::new(flags.folders.into_iter().map(|folder| {
+                std::path::absolute(&folder)
+                    .ok()
+                    .and_then(|path| path.to_str().map(str::to_owned))
+                    .unwrap_or(folder)
+            }));
+            command = Command::batch([
+                command,
+                Command::perform(load_roots(roots), Message::FolderSelected),
+            ]);
+        }
+        if let Some(name) = flags.preset {
+            match mareto.presets.presets.get(&name).cloned() {
+                Some(preset) => {
+                    mareto.presets.selected = Some(name.clone());
+                    mareto.presets.name = name;
+                    command = Command::batch([command, mareto.apply_preset(&preset)]);
+                }
+                None => {
+                    mareto.error = Some(Error::Presets(format!(
+                        "There is no preset named \"{name}\""
+                    )))
+                }
+            }
+        }
+        (mareto, command)
     }
 
     fn title(&self) -> String {
@@ -80,104 +339,385 @@ impl Application for Mareto {
         self.options.theme.selected.clone().unwrap_or_default()
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let watcher = if self.options.watch_folders
+            && !self.editor_state.roots.is_empty()
+            && !self.applying
+        {
+            watcher::watch(self.editor_state.roots.clone())
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            watcher,
+            event::listen_with(|event, status| match event {
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    Some(Message::KeyPressed(key, modifiers, status))
+                }
+                Event::Window(window::Id::MAIN, window::Event::Resized { width, height }) => Some(
+                    Message::WindowResized(Size::new(width as f32, height as f32)),
+                ),
+                Event::Window(window::Id::MAIN, window::Event::CloseRequested) => {
+                    Some(Message::CloseRequested)
+                }
+                Event::Window(window::Id::MAIN, window::Event::FileDropped(path)) => {
+                    Some(Message::FileDropped(path))
+                }
+                _ => None,
+            }),
+        ])
+    }
+
     fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
         match message {
             // Top-level actions
-            Message::OpenFolder => Command::perform(pick_folder(), Message::FolderSelected),
-            Message::FolderSelected(Ok((path, entries))) => {
-                if !self
+            Message::OpenFolder => {
+                Command::perform(pick_folders(Roots::default()), Message::FolderSelected)
+            }
+            Message::AddFolder => Command::perform(
+                pick_folders(self.editor_state.roots.clone()),
+                Message::FolderAdded,
+            ),
+            Message::FolderAdded(Ok((roots, entries))) => {
+                // With a second folder, the paths of the entries start with the label of their
+                // folder, so the pending edits are moved along.
+                let prefix = match self.editor_state.roots.folders.len() {
+                    1 => roots.prefix(0),
+                    _ => String::new(),
+                };
+                let edits = self
                     .editor_state
-                    .open_folder
-                    .as_ref()
-                    .is_some_and(|p| p == path.as_str())
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.is_changed())
+                    .map(|entry| {
+                        (
+                            format!("{prefix}{}", entry.og_path),
+                            format!("{prefix}{}", entry.path),
+                        )
+                    })
+                    .collect();
+                self.open_roots(roots, entries, &edits)
+            }
+            Message::FolderAdded(Err(error)) => {
+                self.show_error(error);
+                Command::none()
+            }
+            Message::RecentFolderSelected(path) => {
+                Command::perform(load_roots(Roots::new([path])), Message::FolderSelected)
+            }
+            Message::FolderSelected(Ok((roots, entries))) => {
+                if self.editor_state.roots != roots {
+                    return self.open_roots(roots, entries, &BTreeMap::new());
+                }
+                Command::none()
+            }
+            Message::FolderSelected(Err(error)) => {
+                self.show_error(error);
+                Command::none()
+            }
+            Message::ApplyChanges => {
+                if self.applying {
+                    return Command::none();
+                }
+                match self.plan() {
+                    Some(plan) => {
+                        // Scanning half renamed folders would report edits as no longer matching.
+                        self.applying = true;
+                        self.applied_count += 1;
+                        Command::perform(
+                            apply_plan(self.editor_state.roots.clone(), plan, self.options.use_git),
+                            Message::ApplyOutcome,
+                        )
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::ApplyOutcome(Ok((roots, entries))) => {
+                self.applying = false;
+                self.error = None;
+                self.open_roots(roots, entries, &BTreeMap::new())
+            }
+            Message::ApplyOutcome(Err(error)) => {
+                self.applying = false;
+                self.show_error(error);
+                // Some entries may have been renamed before it failed, which the paused watcher
+                // didn't see.
+                if self.options.watch_folders {
+                    return self.update(Message::Refresh);
+                }
+                Command::none()
+            }
+            Message::Refresh | Message::FoldersChanged => {
+                if self.editor_state.roots.is_empty() || self.applying {
+                    return Command::none();
+                }
+                let applied_count = self.applied_count;
+                Command::perform(load_roots(self.editor_state.roots.clone()), move |result| {
+                    Message::Refreshed(applied_count, result)
+                })
+            }
+            Message::Refreshed(applied_count, Ok((roots, entries))) => {
+                // Another folder may have been opened, or changes applied, while scanning.
+                if roots != self.editor_state.roots
+                    || self.applying
+                    || applied_count != self.applied_count
                 {
-                    self.filters.resize_filters(entries.len());
-                    self.editor_state.open_folder = Some(path);
-                    self.editor_state.entries = entries;
-                    self.filters.update_text_filter(&self.editor_state);
-                    self.filters.update_min_depth(&self.editor_state);
-                    self.filters.update_max_depth(&self.editor_state);
-                    self.filters.update_show_files(&self.editor_state);
-                    self.filters.update_show_folders(&self.editor_state);
-                    self.editor_state
-                        .show_filtered_entries(&self.options, &self.filters);
+                    return Command::none();
                 }
+                self.filters.resize_filters(entries.len());
+                let removed = self.editor_state.refresh(entries);
+                // The edits of entries a failed apply renamed are gone, as its error explains.
+                if !removed.is_empty() && !matches!(self.error, Some(Error::Apply(_))) {
+                    self.error = Some(Error::UnmatchedEdits {
+                        unmatched: removed,
+                        invalid: Vec::new(),
+                    });
+                }
+                self.update_filters(&FilterKind::ALL)
+            }
+            Message::Refreshed(_, Err(error)) | Message::WatchFailed(error) => {
+                self.error = Some(error);
                 Command::none()
             }
-            Message::FolderSelected(_) => Command::none(),
-            Message::ApplyChanges => Command::none(),
-            //Message::ApplyOutcome(_) => Command::none(),
+            Message::SaveSession => {
+                if self.editor_state.roots.is_empty() {
+                    return Command::none();
+                }
+                let session = Session::new(
+                    &self.editor_state.roots,
+                    &self.filters,
+                    &self.editor_state.entries,
+                );
+                Command::perform(save_session(session), Message::SessionSaved)
+            }
+            Message::SessionSaved(result) => {
+                if let Err(error) = result {
+                    self.show_error(error);
+                }
+                Command::none()
+            }
+            Message::OpenSession => Command::perform(open_session(), Message::SessionOpened),
+            Message::SessionOpened(Ok((session, roots, entries))) => {
+                self.error = None;
+                session.filters.apply(&mut self.filters);
+                let command = self.open_roots(roots, entries, &session.edits);
+                if !session.invalid_edits.is_empty() {
+                    let unmatched = match self.error.take() {
+                        Some(Error::UnmatchedEdits { unmatched, .. }) => unmatched,
+                        _ => Vec::new(),
+                    };
+                    self.error = Some(Error::UnmatchedEdits {
+                        unmatched,
+                        invalid: session.invalid_edits,
+                    });
+                }
+                command
+            }
+            Message::SessionOpened(Err(error)) => {
+                self.show_error(error);
+                Command::none()
+            }
+            Message::ImportMapping => {
+                if self.editor_state.roots.is_empty() {
+                    return Command::none();
+                }
+                Command::perform(import_mapping(), Message::MappingImported)
+            }
+            Message::MappingImported(Ok(rows)) => {
+                self.error = None;
+                let matches =
+                    mapping::resolve(&self.editor_state.roots, &self.editor_state.entries, rows);
+                self.editor_state.apply_edits(&matches.edits);
+                if !matches.unmatched.is_empty()
+                    || !matches.ambiguous.is_empty()
+                    || !matches.outside.is_empty()
+                {
+                    self.error = Some(Error::UnmatchedRows {
+                        unmatched: matches.unmatched,
+                        ambiguous: matches.ambiguous,
+                        outside: matches.outside,
+                    });
+                }
+                self.update_filters(&FilterKind::ALL)
+            }
+            Message::MappingImported(Err(error)) => {
+                self.show_error(error);
+                Command::none()
+            }
+            Message::ExportPlan => match self.plan() {
+                Some(plan) => Command::perform(export_plan(plan), Message::PlanExported),
+                None => Command::none(),
+            },
+            Message::PlanExported(result) => {
+                match result {
+                    Ok(()) => self.error = None,
+                    Err(error) => self.show_error(error),
+                }
+                Command::none()
+            }
+            Message::KeyPressed(key, modifiers, status) => {
+                // A focused text input captures every key press, so only the ones it acts on
+                // itself are left to it, and typing is never taken over.
+                if status == event::Status::Captured
+                    && shortcuts::is_text_input_key(&key, modifiers)
+                {
+                    return Command::none();
+                }
+                match self.shortcuts.action_for(&key, modifiers) {
+                    Some(action) => self.update(action.message()),
+                    None => Command::none(),
+                }
+            }
+            Message::WindowResized(size) => {
+                self.config.window = Some(size.into());
+                Command::none()
+            }
+            Message::FileDropped(path) => {
+                self.dropped_paths.push(path);
+                // Every dropped path is its own event, so the drop is finished once no further
+                // path arrived for a moment.
+                let count = self.dropped_paths.len();
+                Command::perform(
+                    futures_timer::Delay::new(Duration::from_millis(100)),
+                    move |()| Message::DropFinished(count),
+                )
+            }
+            Message::DropFinished(count) => {
+                if count != self.dropped_paths.len() {
+                    return Command::none();
+                }
+                let paths = std::mem::take(&mut self.dropped_paths)
+                    .iter()
+                    .filter_map(|path| path.to_str().map(str::to_owned))
+                    .collect::<Vec<_>>();
+                if paths.is_empty() {
+                    return Command::none();
+                }
+                let is_folder = matches!(&paths[..], [path] if Path::new(path).is_dir());
+                // Dropping entries of the opened folders shows just those.
+                let entry_paths = paths
+                    .iter()
+                    .map(|path| self.editor_state.roots.entry_path(path))
+                    .collect::<Option<Vec<_>>>();
+                if let Some(entry_paths) = entry_paths.filter(|_| !is_folder) {
+                    self.filters.show_exactly(entry_paths);
+                    return self.update_filters(&[FilterKind::Dropped]);
+                }
+                let (roots, dropped) = if is_folder {
+                    (Roots::new(paths), None)
+                } else {
+                    let Some((folder, relative_paths)) = common_parent(&paths) else {
+                        return Command::none();
+                    };
+                    (Roots::new([folder]), Some(relative_paths))
+                };
+                if roots == self.editor_state.roots {
+                    return Command::none();
+                }
+                // Opening other folders loses the pending edits and any further opened folder.
+                let confirm = self.editor_state.pending_edit_count().0 > 0
+                    || self.editor_state.roots.folders.len() > 1;
+                Command::perform(load_dropped_roots(roots, confirm), move |result| {
+                    Message::DroppedPathsLoaded(dropped, result)
+                })
+            }
+            Message::DroppedPathsLoaded(dropped, Ok((roots, entries))) => {
+                let command = self.open_roots(roots, entries, &BTreeMap::new());
+                match dropped {
+                    Some(paths) => {
+                        self.filters.show_exactly(paths);
+                        Command::batch([command, self.update_filters(&[FilterKind::Dropped])])
+                    }
+                    None => command,
+                }
+            }
+            Message::DroppedPathsLoaded(_, Err(error)) => {
+                self.show_error(error);
+                Command::none()
+            }
+            Message::DroppedEntriesCleared => {
+                self.filters.show_all_dropped();
+                self.update_filters(&[FilterKind::Dropped])
+            }
+            Message::CloseRequested => {
+                if self.save_config {
+                    self.config.update(&self.options, &self.filters);
+                    // The window stays open to show why, and closes without saving next time.
+                    if let Err(error) = self.config.save() {
+                        self.save_config = false;
+                        self.error = Some(Error::ConfigNotSaved(error.to_string()));
+                        return Command::none();
+                    }
+                }
+                window::close(window::Id::MAIN)
+            }
+            Message::FocusFilter => focus(ui::filter_input()),
+            Message::FocusFind => focus(ui::find_input()),
+            Message::FocusReplace => focus(ui::replace_input()),
 
             // Options updates
             Message::FilterUpdated(filter) => {
                 self.filters.filter_input.state.input = filter;
-                self.filters.update_text_filter(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::Text])
             }
             Message::FilterRegexToggled => {
                 self.filters.filter_input.state.use_regex =
                     !self.filters.filter_input.state.use_regex;
-                self.filters.update_text_filter(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::Text])
             }
             Message::FilterCaseSensitivityToggled => {
                 self.filters.filter_input.state.case_sensitive =
                     !self.filters.filter_input.state.case_sensitive;
-                self.filters.update_text_filter(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::Text])
             }
             Message::MinDepthToggled(is_active) => {
                 self.filters.min_depth.state.is_active = is_active;
-                self.filters.update_min_depth(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::MinDepth])
             }
             Message::MinDepthLimitChanged(limit) => {
                 self.filters.min_depth.state.set_from_str(limit);
                 self.filters.normalize_max_depth();
-                self.filters.update_min_depth(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::MinDepth, FilterKind::MaxDepth])
             }
             Message::MaxDepthToggled(is_active) => {
                 self.filters.max_depth.state.is_active = is_active;
-                self.filters.update_min_depth(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::MaxDepth])
             }
             Message::MaxDepthLimitChanged(limit) => {
                 self.filters.max_depth.state.set_from_str(limit);
                 self.filters.normalize_min_depth();
-                self.filters.update_max_depth(&self.editor_state);
-                self.editor_state
-                    .show_filtered_entries(&self.options, &self.filters);
-                Command::none()
+                self.update_filters(&[FilterKind::MinDepth, FilterKind::MaxDepth])
             }
             Message::ShowFilesToggled(is_active) => {
                 self.filters.show_files.state = is_active;
-                self.filters.update_show_files(&self.editor_state);
+                self.update_filters(&[FilterKind::ShowFiles])
+            }
+            Message::ShowFoldersToggled(is_active) => {
+                self.filters.show_folders.state = is_active;
+                self.update_filters(&[FilterKind::ShowFolders])
+            }
+            Message::FiltersUpdated(result) => {
+                if self.filters.apply_job_result(result) {
+                    self.editor_state
+                        .show_filtered_entries(&self.options, &self.filters);
+                }
+                Command::none()
+            }
+            Message::SortOrderSelected(order) => {
+                self.options.sorting.selected = Some(order);
                 self.editor_state
                     .show_filtered_entries(&self.options, &self.filters);
                 Command::none()
             }
-            Message::ShowFoldersToggled(is_active) => {
-                self.filters.show_folders.state = is_active;
-                self.filters.update_show_folders(&self.editor_state);
+            Message::SecondarySortOrderSelected(order) => {
+                self.options.secondary_sorting.selected = Some(order);
                 self.editor_state
                     .show_filtered_entries(&self.options, &self.filters);
                 Command::none()
             }
-            Message::SortOrderSelected(order) => {
-                self.options.sorting.selected = Some(order);
+            Message::DirectoriesFirstToggled(is_active) => {
+                self.options.directories_first = is_active;
                 self.editor_state
                     .show_filtered_entries(&self.options, &self.filters);
                 Command::none()
@@ -188,6 +728,12 @@ impl Application for Mareto {
                     .show_filtered_entries(&self.options, &self.filters);
                 Command::none()
             }
+            Message::ShowOnlyChangedToggled(is_active) => {
+                self.options.show_only_changed = is_active;
+                self.editor_state
+                    .show_filtered_entries(&self.options, &self.filters);
+                Command::none()
+            }
             Message::RemoveFoldersToggled(is_active) => {
                 self.options.remove_empty = is_active;
                 Command::none()
@@ -196,32 +742,183 @@ impl Application for Mareto {
                 self.options.preview_changes = is_active;
                 Command::none()
             }
+            Message::AllowOutsideRootToggled(is_active) => {
+                self.options.allow_outside_root = is_active;
+                Command::none()
+            }
+            Message::WatchFoldersToggled(is_active) => {
+                self.options.watch_folders = is_active;
+                Command::none()
+            }
+            Message::UseGitToggled(is_active) => {
+                self.options.use_git = is_active;
+                self.detect_repositories()
+            }
+            Message::RepositoriesDetected(roots, repositories) => {
+                if roots == self.editor_state.roots {
+                    self.repositories = repositories;
+                }
+                Command::none()
+            }
             Message::ThemeSelected(theme) => {
                 self.options.theme.selected = Some(theme);
                 Command::none()
             }
 
+            // Presets
+            Message::PresetNameUpdated(name) => {
+                self.presets.name = name;
+                Command::none()
+            }
+            Message::PresetSelected(name) => {
+                self.presets.name = name.clone();
+                self.presets.selected = Some(name);
+                Command::none()
+            }
+            Message::SavePreset => {
+                let name = self.presets.name.trim().to_owned();
+                if name.is_empty() {
+                    return Command::none();
+                }
+                let preset = Preset::new(
+                    &self.options,
+                    &self.filters,
+                    &self.editor_state.find_and_replace,
+                );
+                self.presets.presets.insert(name.clone(), preset);
+                self.presets.selected = Some(name);
+                self.save_presets();
+                Command::none()
+            }
+            Message::LoadPreset => {
+                let preset = self
+                    .presets
+                    .selected
+                    .as_ref()
+                    .and_then(|name| self.presets.presets.get(name))
+                    .cloned();
+                match preset {
+                    Some(preset) => self.apply_preset(&preset),
+                    None => Command::none(),
+                }
+            }
+            Message::RenamePreset => {
+                let name = self.presets.name.trim().to_owned();
+                let Some(selected) = self.presets.selected.clone() else {
+                    return Command::none();
+                };
+                if name.is_empty() || name == selected {
+                    return Command::none();
+                }
+                if self.presets.presets.rename(&selected, name.clone()) {
+                    self.presets.selected = Some(name);
+                    self.save_presets();
+                } else {
+                    self.error = Some(Error::Presets(format!(
+                        "There already is a preset named \"{name}\""
+                    )));
+                }
+                Command::none()
+            }
+            Message::DeletePreset => {
+                if let Some(selected) = self.presets.selected.take() {
+                    self.presets.presets.remove(&selected);
+                    self.save_presets();
+                }
+                Command::none()
+            }
+
             // Editor actions
             Message::EditAction(action) => {
                 self.editor_state.handle_action(action);
                 Command::none()
             }
+            Message::CursorAction(action) => {
+                self.editor_state.handle_cursor_action(action);
+                Command::none()
+            }
+            Message::ScrollEditorTo(offset) => {
+                scrollable::scroll_to(ui::editor_scrollable(), offset)
+            }
+            Message::ResetCurrentEntry => {
+                self.editor_state.reset_current_entry();
+                Command::none()
+            }
+            Message::ResetAllEntries => {
+                self.editor_state.reset_all_entries();
+                Command::none()
+            }
+            Message::Undo => {
+                self.editor_state.undo();
+                Command::none()
+            }
+            Message::Redo => {
+                self.editor_state.redo();
+                Command::none()
+            }
+
+            // Find and replace
+            Message::FindUpdated(input) => {
+                self.editor_state.find_and_replace.find.input = input;
+                self.editor_state.find_and_replace.find.update_regex();
+                self.editor_state.update_occurences();
+                Command::none()
+            }
+            Message::FindCaseSensitivityToggled => {
+                let find = &mut self.editor_state.find_and_replace.find;
+                find.case_sensitive = !find.case_sensitive;
+                find.update_regex();
+                self.editor_state.update_occurences();
+                Command::none()
+            }
+            Message::FindRegexToggled => {
+                let find = &mut self.editor_state.find_and_replace.find;
+                find.use_regex = !find.use_regex;
+                find.update_regex();
+                self.editor_state.update_occurences();
+                Command::none()
+            }
+            Message::ReplaceUpdated(replace) => {
+                self.editor_state.find_and_replace.replace = replace;
+                Command::none()
+            }
+            Message::FindNext => {
+                self.editor_state.find_next(true);
+                Command::none()
+            }
+            Message::FindPrevious => {
+                self.editor_state.find_next(false);
+                Command::none()
+            }
+            Message::ReplaceCurrent => {
+                self.editor_state.replace_current();
+                Command::none()
+            }
+            Message::ReplaceAll => {
+                self.editor_state.replace_all();
+                Command::none()
+            }
         }
     }
 
     fn view(&self) -> Element<'_, Self::Message, Self::Theme, iced::Renderer> {
         let left_pane = column![
-            ui::top_level_actions(),
+            ui::top_level_actions(&self.config.recent_folders, self.error.as_ref()),
             Rule::horizontal(1),
             text("Options"),
-            ui::options(&self.options, &self.filters),
+            ui::options(
+                &self.options,
+                &self.filters,
+                &self.presets,
+                &self.repositories,
+            ),
         ]
         .width(400)
         .spacing(12);
 
         let right_pane = column![
-            ui::editor(&self.editor_state),
-            ui::find_and_replace(self.editor_state.open_folder.as_deref().unwrap_or("")),
+            ui::editor(&self.editor_state, &self.shortcuts),
+            ui::find_and_replace(&self.editor_state),
         ]
         .spacing(12);
 
@@ -231,14 +928,243 @@ impl Application for Mareto {
     }
 }
 
-async fn pick_folder() -> Result<(String, Vec<FileSystemEntry>), Error> {
-    let path = rfd::AsyncFileDialog::new()
-        .set_title("Choose a folder...")
-        .pick_folder()
+impl Mareto {
+    /// Shows the entries of the given folders, with the pending edits keyed by original path.
+    fn open_roots(
+        &mut self,
+        roots: Roots,
+        entries: Vec<FileSystemEntry>,
+        edits: &BTreeMap<String, String>,
+    ) -> Command<Message> {
+        for folder in &roots.folders {
+            self.config.add_recent_folder(folder.clone());
+        }
+        self.filters.resize_filters(entries.len());
+        self.filters.show_all_dropped();
+        self.editor_state.open(roots, entries);
+        let unmatched = self.editor_state.apply_edits(edits);
+        if !unmatched.is_empty() {
+            self.error = Some(Error::UnmatchedEdits {
+                unmatched,
+                invalid: Vec::new(),
+            });
+        }
+        Command::batch([
+            self.update_filters(&FilterKind::ALL),
+            self.detect_repositories(),
+        ])
+    }
+
+    /// Works out how to apply the pending changes, showing why if they can't be applied.
+    fn plan(&mut self) -> Option<Plan> {
+        if self.editor_state.roots.is_empty() {
+            return None;
+        }
+        match apply::plan(
+            &self.editor_state.roots,
+            &self.editor_state.entries,
+            self.options.allow_outside_root,
+        ) {
+            Ok(plan) => Some(plan),
+            Err(errors) => {
+                self.error = Some(Error::Plan(errors));
+                None
+            }
+        }
+    }
+
+    /// Looks for the git repositories of the opened folders in the background, if tracked files
+    /// are renamed with git.
+    fn detect_repositories(&self) -> Command<Message> {
+        if !self.options.use_git || self.editor_state.roots.is_empty() {
+            return Command::none();
+        }
+        let roots = self.editor_state.roots.clone();
+        Command::perform(
+            async move {
+                let repositories = Repository::detect(&roots);
+                (roots, repositories)
+            },
+            |(roots, repositories)| Message::RepositoriesDetected(roots, repositories),
+        )
+    }
+
+    /// Shows an error, unless it's only about a dialog being closed.
+    fn show_error(&mut self, error: Error) {
+        if !matches!(error, Error::DialogClosed) {
+            self.error = Some(error);
+        }
+    }
+
+    /// Takes over the options, filters and find and replace of a preset.
+    fn apply_preset(&mut self, preset: &Preset) -> Command<Message> {
+        preset.apply(
+            &mut self.options,
+            &mut self.filters,
+            &mut self.editor_state.find_and_replace,
+        );
+        self.editor_state.find_and_replace.find.update_regex();
+        self.editor_state.update_occurences();
+        self.update_filters(&FilterKind::ALL)
+    }
+
+    fn save_presets(&mut self) {
+        if !self.presets.is_loaded {
+            self.error = Some(Error::Presets(
+                "The preset file could not be read, so changes to the presets are not saved"
+                    .to_owned(),
+            ));
+            return;
+        }
+        if let Err(error) = self.presets.presets.save() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Recomputes the given filters on a background thread. The editor is updated once the
+    /// result arrives, unless another update was started in the meantime.
+    fn update_filters(&mut self, kinds: &[FilterKind]) -> Command<Message> {
+        let job = self.filters.start_job(kinds, &self.editor_state.entries);
+        Command::perform(async move { job.run() }, Message::FiltersUpdated)
+    }
+}
+
+fn focus(id: text_input::Id) -> Command<Message> {
+    Command::batch([text_input::focus(id.clone()), text_input::select_all(id)])
+}
+
+async fn apply_plan(
+    roots: Roots,
+    plan: Plan,
+    use_git: bool,
+) -> Result<(Roots, Vec<FileSystemEntry>), Error> {
+    apply::verify(&plan).map_err(Error::SourcesChanged)?;
+    let mut tracked = Tracked::default();
+    if use_git {
+        let repositories = Repository::detect(&roots);
+        let paths = plan
+            .sources
+            .iter()
+            .map(|source| source.path.as_str())
+            .collect::<Vec<_>>();
+        confirm_uncommitted(&repositories, &paths).await?;
+        tracked = Tracked::find(repositories, &paths)?;
+    }
+    apply::execute(&plan, &mut tracked)?;
+    load_roots(roots).await
+}
+
+/// Asks whether to go ahead if any of the entries to rename has changes that aren't committed
+/// yet, as `git mv` stages the rename along with those changes.
+async fn confirm_uncommitted(repositories: &[Repository], paths: &[&str]) -> Result<(), Error> {
+    let uncommitted = git::uncommitted(repositories, paths)?;
+    if uncommitted.is_empty() {
+        return Ok(());
+    }
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Uncommitted changes")
+        .set_description(format!(
+            "These files have changes that aren't committed yet:\n{}\n\nRename them anyway?",
+            uncommitted.join("\n")
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+    match answer {
+        rfd::MessageDialogResult::Yes => Ok(()),
+        _ => Err(Error::DialogClosed),
+    }
+}
+
+async fn save_session(session: Session) -> Result<(), Error> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Save session...")
+        .set_file_name("session.toml")
+        .add_filter("Mareto session", &["toml"])
+        .save_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+    session.save(file.path())
+}
+
+/// Reads a session and scans its folders again, as the entries may have changed since.
+async fn open_session() -> Result<(Session, Roots, Vec<FileSystemEntry>), Error> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Open session...")
+        .add_filter("Mareto session", &["toml"])
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+    let session = Session::load(file.path())?;
+    let (roots, entries) = load_roots(Roots::new(session.folders.clone())).await?;
+
+    Ok((session, roots, entries))
+}
+
+/// Opens the folder of a drop, after asking whether to close the opened folders along with
+/// their pending edits if `confirm` is set.
+async fn load_dropped_roots(
+    roots: Roots,
+    confirm: bool,
+) -> Result<(Roots, Vec<FileSystemEntry>), Error> {
+    if confirm {
+        let answer = rfd::AsyncMessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title("Open dropped entries")
+            .set_description(
+                "Opening the dropped entries closes the opened folders and discards their pending \
+                 edits. Open them anyway?",
+            )
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show()
+            .await;
+        match answer {
+            rfd::MessageDialogResult::Yes => {}
+            _ => return Err(Error::DialogClosed),
+        }
+    }
+    load_roots(roots).await
+}
+
+/// Lets the user choose one or more folders to open in addition to the given ones.
+async fn pick_folders(mut roots: Roots) -> Result<(Roots, Vec<FileSystemEntry>), Error> {
+    let folders = rfd::AsyncFileDialog::new()
+        .set_title("Choose folders...")
+        .pick_folders()
         .await
-        .and_then(|fh| fh.path().to_str().map(|s| s.to_owned()))
         .ok_or(Error::DialogClosed)?;
-    let entries = get_entries_for_path(&path)?;
+    for folder in folders {
+        if let Some(path) = folder.path().to_str() {
+            roots.push(path.to_owned());
+        }
+    }
+    load_roots(roots).await
+}
+
+async fn import_mapping() -> Result<Vec<(String, String)>, Error> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Import mapping...")
+        .add_filter("Mapping", &mapping::Format::EXTENSIONS)
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+    mapping::load(file.path())
+}
+
+async fn export_plan(plan: Plan) -> Result<(), Error> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Export plan...")
+        .set_file_name("plan.csv")
+        .add_filter("Plan", &mapping::EXPORT_EXTENSIONS)
+        .save_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+    mapping::export(&plan, file.path())
+}
+
+async fn load_roots(roots: Roots) -> Result<(Roots, Vec<FileSystemEntry>), Error> {
+    let entries = get_entries_for_roots(&roots)?;
 
-    Ok((path, entries))
+    Ok((roots, entries))
 }
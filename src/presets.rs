@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{config_folder, FiltersConfig},
+    mareto::Error,
+    state::{DisplayType, FilterOptions, FindAndReplace, Options, SortingOption},
+};
+
+/// A named setup of filters, sorting, display type and find and replace, so that a recurring
+/// renaming job can be set up again in one go.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    pub sorting: SortingOption,
+    pub secondary_sorting: SortingOption,
+    pub directories_first: bool,
+    pub display_type: DisplayType,
+    pub filters: FiltersConfig,
+    pub find_and_replace: FindAndReplaceConfig,
+}
+
+impl Preset {
+    pub fn new(
+        options: &Options,
+        filters: &FilterOptions,
+        find_and_replace: &FindAndReplace,
+    ) -> Self {
+        Self {
+            sorting: options.sorting.selected.unwrap_or(SortingOption::NoSorting),
+            secondary_sorting: options
+                .secondary_sorting
+                .selected
+                .unwrap_or(SortingOption::NoSorting),
+            directories_first: options.directories_first,
+            display_type: options
+                .display_type
+                .selected
+                .unwrap_or(DisplayType::RelativePath),
+            filters: FiltersConfig::from(filters),
+            find_and_replace: FindAndReplaceConfig {
+                find: find_and_replace.find.input.clone(),
+                replace: find_and_replace.replace.clone(),
+                use_regex: find_and_replace.find.use_regex,
+                case_sensitive: find_and_replace.find.case_sensitive,
+            },
+        }
+    }
+
+    /// Sets the options, filters and find and replace to the ones of the preset. The filter
+    /// and find regexes still have to be updated afterwards.
+    pub fn apply(
+        &self,
+        options: &mut Options,
+        filters: &mut FilterOptions,
+        find_and_replace: &mut FindAndReplace,
+    ) {
+        options.sorting.selected = Some(self.sorting);
+        options.secondary_sorting.selected = Some(self.secondary_sorting);
+        options.directories_first = self.directories_first;
+        options.display_type.selected = Some(self.display_type);
+        self.filters.apply(filters);
+        find_and_replace.find.input = self.find_and_replace.find.clone();
+        find_and_replace.find.use_regex = self.find_and_replace.use_regex;
+        find_and_replace.find.case_sensitive = self.find_and_replace.case_sensitive;
+        find_and_replace.replace = self.find_and_replace.replace.clone();
+    }
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self::new(
+            &Options::default(),
+            &FilterOptions::default(),
+            &FindAndReplace::default(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FindAndReplaceConfig {
+    pub find: String,
+    pub replace: String,
+    pub use_regex: bool,
+    pub case_sensitive: bool,
+}
+
+/// The presets, stored in `presets.toml` next to the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Presets {
+    pub presets: BTreeMap<String, Preset>,
+}
+
+impl Presets {
+    /// Reads the preset file. If there is no preset file yet, there are no presets.
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = config_folder().map(|folder| folder.join("presets.toml")) else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(Error::Presets(format!(
+                "The preset file could not be read: {error}"
+            ))),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(folder) = config_folder() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&folder)?;
+        std::fs::write(folder.join("presets.toml"), self.to_toml())?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.presets.keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    /// Stores a preset under the given name, replacing a preset with the same name.
+    pub fn insert(&mut self, name: String, preset: Preset) {
+        self.presets.insert(name, preset);
+    }
+
+    /// Gives a preset a new name. Returns false if there is no preset with the old name or if
+    /// the new name is already taken.
+    pub fn rename(&mut self, from: &str, to: String) -> bool {
+        if self.presets.contains_key(&to) {
+            return false;
+        }
+        match self.presets.remove(from) {
+            Some(preset) => {
+                self.presets.insert(to, preset);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.presets.remove(name);
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        toml::from_str(contents)
+            .map_err(|error| Error::Presets(format!("The preset file could not be read: {error}")))
+    }
+
+    fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Presets can always be serialized")
+    }
+}
+
+/// The presets together with what is entered in the presets section of the options pane.
+#[derive(Debug, Default)]
+pub struct PresetsState {
+    pub presets: Presets,
+    pub selected: Option<String>,
+    pub name: String,
+    /// Whether the preset file was read. If it couldn't be, it is never overwritten.
+    pub is_loaded: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::state::DepthLimit;
+
+    #[test]
+    fn test_presets() {
+        let mut options = Options::default();
+        options.sorting.selected = Some(SortingOption::SortNaturalAscending);
+        let mut filters = FilterOptions::default();
+        filters.filter_input.state.input = r"\.JPG$".to_owned();
+        filters.filter_input.state.use_regex = true;
+        filters.max_depth.state = DepthLimit {
+            is_active: true,
+            limit: Some(1),
+        };
+        let mut find_and_replace = FindAndReplace::default();
+        find_and_replace.find.input = "IMG_".to_owned();
+        find_and_replace.replace = "holiday_".to_owned();
+
+        let mut presets = Presets::default();
+        presets.insert(
+            "camera dumps".to_owned(),
+            Preset::new(&options, &filters, &find_and_replace),
+        );
+        presets.insert("other".to_owned(), Preset::default());
+        let mut presets = Presets::parse(&presets.to_toml()).unwrap();
+        assert_eq!(presets.names(), ["camera dumps", "other"]);
+
+        assert!(!presets.rename("camera dumps", "other".to_owned()));
+        assert!(!presets.rename("missing", "new".to_owned()));
+        assert!(presets.rename("camera dumps", "weekly".to_owned()));
+        presets.remove("other");
+        assert_eq!(presets.names(), ["weekly"]);
+
+        let mut loaded_options = Options::default();
+        let mut loaded_filters = FilterOptions::default();
+        let mut loaded_find_and_replace = FindAndReplace::default();
+        presets.get("weekly").unwrap().apply(
+            &mut loaded_options,
+            &mut loaded_filters,
+            &mut loaded_find_and_replace,
+        );
+        assert_eq!(
+            loaded_options.sorting.selected,
+            Some(SortingOption::SortNaturalAscending)
+        );
+        assert_eq!(loaded_filters.filter_input.state.input, r"\.JPG$");
+        assert!(loaded_filters.filter_input.state.use_regex);
+        assert_eq!(loaded_filters.max_depth.state.limit, Some(1));
+        assert_eq!(loaded_find_and_replace.find.input, "IMG_");
+        assert_eq!(loaded_find_and_replace.replace, "holiday_");
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::FiltersConfig,
+    fs::{is_invalid_name_char, FileSystemEntry, Roots},
+    mareto::Error,
+    state::FilterOptions,
+};
+
+/// An interrupted renaming job: the opened folders, the filters and the pending new path of
+/// every changed entry, keyed by its original path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    pub folders: Vec<String>,
+    #[serde(default)]
+    pub filters: FiltersConfig,
+    #[serde(default)]
+    pub edits: BTreeMap<String, String>,
+    /// Edits that were left out when reading the session, as their new path is not one the
+    /// editor could have made.
+    #[serde(skip)]
+    pub invalid_edits: Vec<(String, String)>,
+}
+
+impl Session {
+    pub fn new(roots: &Roots, filters: &FilterOptions, entries: &[FileSystemEntry]) -> Self {
+        let edits = entries
+            .iter()
+            .filter(|entry| entry.is_changed())
+            .map(|entry| (entry.og_path.clone(), entry.path.clone()))
+            .collect();
+        Self {
+            folders: roots.folders.clone(),
+            filters: FiltersConfig::from(filters),
+            edits,
+            invalid_edits: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// Reads a session, moving the edits with a new path that isn't normalized or that contains
+    /// invalid characters to [`Session::invalid_edits`].
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let mut session: Self =
+            toml::from_str(contents).map_err(|error| Error::Session(error.to_string()))?;
+        let roots = Roots::new(session.folders.clone());
+        for (og_path, path) in std::mem::take(&mut session.edits) {
+            if roots.normalize(&path) == path && !path.contains(is_invalid_name_char) {
+                session.edits.insert(og_path, path);
+            } else {
+                session.invalid_edits.push((og_path, path));
+            }
+        }
+        Ok(session)
+    }
+
+    fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("A session can always be serialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    use crate::fs::EntryType;
+
+    #[test]
+    fn test_round_trip() {
+        let mut filters = FilterOptions::default();
+        filters.filter_input.state.input = "IMG".to_owned();
+        let entries = [
+            FileSystemEntry::for_test("/a/IMG_1.jpg", "/a/holiday_1.jpg", EntryType::File),
+            FileSystemEntry::for_test("/a/IMG_2.jpg", "/a/IMG_2.jpg", EntryType::File),
+            FileSystemEntry::for_test("/b = \"c\".txt", "/b.txt", EntryType::File),
+        ];
+        let roots = Roots::new(["/home/photos".to_owned()]);
+        let session = Session::new(&roots, &filters, &entries);
+        let session = Session::parse(&session.to_toml()).unwrap();
+
+        assert_eq!(session.folders, ["/home/photos"]);
+        assert_eq!(session.filters.input, "IMG");
+        assert_eq!(
+            session.edits,
+            BTreeMap::from([
+                ("/a/IMG_1.jpg".to_owned(), "/a/holiday_1.jpg".to_owned()),
+                ("/b = \"c\".txt".to_owned(), "/b.txt".to_owned()),
+            ])
+        );
+
+        assert!(session.invalid_edits.is_empty());
+
+        assert!(Session::parse("edits = {}").is_err());
+        assert!(Session::parse("folders = [\"/\"]\nfolder = \"/\"").is_err());
+    }
+
+    #[rstest]
+    #[case::no_separator("b.jpg")]
+    #[case::empty("")]
+    #[case::not_normalized("/x/../b.jpg")]
+    #[case::invalid_char("/b\u{7}.jpg")]
+    fn test_invalid_edits(#[case] path: &str) {
+        let session = Session {
+            folders: vec!["/home/photos".to_owned()],
+            filters: FiltersConfig::default(),
+            edits: BTreeMap::from([
+                ("/a.jpg".to_owned(), path.to_owned()),
+                ("/c.jpg".to_owned(), "/d.jpg".to_owned()),
+            ]),
+            invalid_edits: Vec::new(),
+        };
+        let contents = session.to_toml();
+        let session = Session::parse(&contents).unwrap();
+        assert_eq!(
+            session.edits,
+            BTreeMap::from([("/c.jpg".to_owned(), "/d.jpg".to_owned())])
+        );
+        assert_eq!(
+            session.invalid_edits,
+            [("/a.jpg".to_owned(), path.to_owned())]
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use iced::keyboard::{key::Named, Key, Modifiers};
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
+
+use crate::mareto::Message;
+
+/// The top-level actions that can be triggered with a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    OpenFolder,
+    ApplyChanges,
+    Refresh,
+    FocusFilter,
+    ToggleFilterRegex,
+    ToggleFilterCaseSensitivity,
+    FocusFind,
+    FocusReplace,
+    FindNext,
+    FindPrevious,
+    Undo,
+    Redo,
+}
+
+impl ShortcutAction {
+    const ALL: [ShortcutAction; 12] = [
+        ShortcutAction::OpenFolder,
+        ShortcutAction::ApplyChanges,
+        ShortcutAction::Refresh,
+        ShortcutAction::FocusFilter,
+        ShortcutAction::ToggleFilterRegex,
+        ShortcutAction::ToggleFilterCaseSensitivity,
+        ShortcutAction::FocusFind,
+        ShortcutAction::FocusReplace,
+        ShortcutAction::FindNext,
+        ShortcutAction::FindPrevious,
+        ShortcutAction::Undo,
+        ShortcutAction::Redo,
+    ];
+
+    fn default_shortcut(self) -> &'static str {
+        match self {
+            ShortcutAction::OpenFolder => "Ctrl+O",
+            ShortcutAction::ApplyChanges => "Ctrl+Enter",
+            ShortcutAction::Refresh => "F5",
+            ShortcutAction::FocusFilter => "Ctrl+L",
+            ShortcutAction::ToggleFilterRegex => "Alt+R",
+            ShortcutAction::ToggleFilterCaseSensitivity => "Alt+C",
+            ShortcutAction::FocusFind => "Ctrl+F",
+            ShortcutAction::FocusReplace => "Ctrl+H",
+            ShortcutAction::FindNext => "F3",
+            ShortcutAction::FindPrevious => "Shift+F3",
+            ShortcutAction::Undo => "Ctrl+Z",
+            ShortcutAction::Redo => "Ctrl+Shift+Z",
+        }
+    }
+
+    pub fn message(self) -> Message {
+        match self {
+            ShortcutAction::OpenFolder => Message::OpenFolder,
+            ShortcutAction::ApplyChanges => Message::ApplyChanges,
+            ShortcutAction::Refresh => Message::Refresh,
+            ShortcutAction::FocusFilter => Message::FocusFilter,
+            ShortcutAction::ToggleFilterRegex => Message::FilterRegexToggled,
+            ShortcutAction::ToggleFilterCaseSensitivity => Message::FilterCaseSensitivityToggled,
+            ShortcutAction::FocusFind => Message::FocusFind,
+            ShortcutAction::FocusReplace => Message::FocusReplace,
+            ShortcutAction::FindNext => Message::FindNext,
+            ShortcutAction::FindPrevious => Message::FindPrevious,
+            ShortcutAction::Undo => Message::Undo,
+            ShortcutAction::Redo => Message::Redo,
+        }
+    }
+}
+
+impl FromStr for ShortcutAction {
+    type Err = serde::de::value::Error;
+
+    /// Parses the name of an action as it is written in the config file, like `find_next`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+/// A key together with the modifiers that have to be held, written like `Ctrl+Shift+Z`.
+///
+/// `Ctrl` stands for the platform's command key, which is Cmd on macOS.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut {
+    key: ShortcutKey,
+    modifiers: Modifiers,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ShortcutKey {
+    Character(String),
+    Named(Named),
+}
+
+impl Shortcut {
+    pub fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        let is_same_key = match (&self.key, key) {
+            (ShortcutKey::Character(c), Key::Character(pressed)) => *c == pressed.to_lowercase(),
+            (ShortcutKey::Named(named), Key::Named(pressed)) => named == pressed,
+            _ => false,
+        };
+        is_same_key && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::empty();
+        let mut parts = s.split('+').map(str::trim).collect::<Vec<_>>();
+        // A trailing `+` is the plus key itself, as in `Ctrl++`.
+        if s.ends_with("++") {
+            parts.pop();
+            *parts
+                .last_mut()
+                .expect("Splitting yields at least one part") = "+";
+        }
+        let key = parts.pop().filter(|key| !key.is_empty());
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "cmd" => Modifiers::COMMAND,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "super" | "logo" => Modifiers::LOGO,
+                _ => return Err(format!("Unknown modifier \"{modifier}\" in \"{s}\"")),
+            };
+        }
+        let key = key.ok_or_else(|| format!("Missing key in \"{s}\""))?;
+        let named = NAMED_KEYS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key));
+        let key = match named {
+            Some((_, named)) => ShortcutKey::Named(*named),
+            None if key.chars().count() == 1 => ShortcutKey::Character(key.to_lowercase()),
+            None => return Err(format!("Unknown key \"{key}\" in \"{s}\"")),
+        };
+        Ok(Self { key, modifiers })
+    }
+}
+
+impl Display for Shortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let logo = if Modifiers::COMMAND == Modifiers::LOGO {
+            Modifiers::empty()
+        } else {
+            Modifiers::LOGO
+        };
+        for (modifier, name) in [
+            (Modifiers::COMMAND, "Ctrl"),
+            (logo, "Super"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::SHIFT, "Shift"),
+        ] {
+            if !modifier.is_empty() && self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match &self.key {
+            ShortcutKey::Character(c) => write!(f, "{}", c.to_uppercase()),
+            ShortcutKey::Named(named) => {
+                let (name, _) = NAMED_KEYS
+                    .iter()
+                    .find(|(_, key)| key == named)
+                    .expect("Only named keys from the list can be parsed");
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(value: Shortcut) -> Self {
+        value.to_string()
+    }
+}
+
+const NAMED_KEYS: [(&str, Named); 27] = [
+    ("Enter", Named::Enter),
+    ("Escape", Named::Escape),
+    ("Tab", Named::Tab),
+    ("Space", Named::Space),
+    ("Backspace", Named::Backspace),
+    ("Delete", Named::Delete),
+    ("Insert", Named::Insert),
+    ("Home", Named::Home),
+    ("End", Named::End),
+    ("PageUp", Named::PageUp),
+    ("PageDown", Named::PageDown),
+    ("Up", Named::ArrowUp),
+    ("Down", Named::ArrowDown),
+    ("Left", Named::ArrowLeft),
+    ("Right", Named::ArrowRight),
+    ("F1", Named::F1),
+    ("F2", Named::F2),
+    ("F3", Named::F3),
+    ("F4", Named::F4),
+    ("F5", Named::F5),
+    ("F6", Named::F6),
+    ("F7", Named::F7),
+    ("F8", Named::F8),
+    ("F9", Named::F9),
+    ("F10", Named::F10),
+    ("F11", Named::F11),
+    ("F12", Named::F12),
+];
+
+/// Whether a focused text input or the editor acts on a key press itself: typing, deleting,
+/// moving the cursor and the clipboard shortcuts. Other key presses are shortcuts even then.
+pub fn is_text_input_key(key: &Key, modifiers: Modifiers) -> bool {
+    let is_plain = !(modifiers.command() || modifiers.alt() || modifiers.logo());
+    match key {
+        Key::Character(c) => {
+            is_plain
+                || (modifiers.command()
+                    && matches!(c.to_lowercase().as_str(), "a" | "c" | "v" | "x"))
+        }
+        Key::Named(named) => {
+            matches!(
+                named,
+                Named::Backspace
+                    | Named::Delete
+                    | Named::ArrowLeft
+                    | Named::ArrowRight
+                    | Named::ArrowUp
+                    | Named::ArrowDown
+                    | Named::Home
+                    | Named::End
+            ) || (is_plain && matches!(named, Named::Enter | Named::Space))
+        }
+        _ => false,
+    }
+}
+
+/// The shortcut of every action, with the defaults replaced by what the user configured.
+#[derive(Debug, Clone)]
+pub struct Shortcuts {
+    bindings: BTreeMap<ShortcutAction, Shortcut>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        let bindings = ShortcutAction::ALL
+            .into_iter()
+            .map(|action| {
+                let shortcut = action
+                    .default_shortcut()
+                    .parse()
+                    .expect("The default shortcuts are valid");
+                (action, shortcut)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Shortcuts {
+    /// Replaces the shortcuts of the actions in `overrides`, which are keyed by the name of the
+    /// action. Returns the names that aren't an action, such as ones of a newer version.
+    pub fn remap(&mut self, overrides: &BTreeMap<String, Shortcut>) -> Vec<String> {
+        let mut unknown = Vec::new();
+        for (name, shortcut) in overrides {
+            match name.parse() {
+                Ok(action) => {
+                    self.bindings.insert(action, shortcut.clone());
+                }
+                Err(_) => unknown.push(name.clone()),
+            }
+        }
+        unknown
+    }
+
+    pub fn action_for(&self, key: &Key, modifiers: Modifiers) -> Option<ShortcutAction> {
+        self.bindings
+            .iter()
+            .find(|(_, shortcut)| shortcut.matches(key, modifiers))
+            .map(|(action, _)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    fn character(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[rstest]
+    #[case("Ctrl+O", character("o"), Modifiers::COMMAND, true)]
+    #[case("ctrl + o", character("O"), Modifiers::COMMAND, true)]
+    #[case("Ctrl+O", character("o"), Modifiers::COMMAND | Modifiers::SHIFT, false)]
+    #[case("Ctrl+Shift+Z", character("Z"), Modifiers::COMMAND | Modifiers::SHIFT, true)]
+    #[case("F3", Key::Named(Named::F3), Modifiers::empty(), true)]
+    #[case("F3", Key::Named(Named::F3), Modifiers::SHIFT, false)]
+    #[case("Ctrl+Enter", Key::Named(Named::Enter), Modifiers::COMMAND, true)]
+    #[case("Alt+R", character("r"), Modifiers::ALT, true)]
+    #[case("Ctrl++", character("+"), Modifiers::COMMAND, true)]
+    fn test_shortcut_matches(
+        #[case] shortcut: &str,
+        #[case] key: Key,
+        #[case] modifiers: Modifiers,
+        #[case] matches: bool,
+    ) {
+        let shortcut = shortcut.parse::<Shortcut>().unwrap();
+        assert_eq!(shortcut.matches(&key, modifiers), matches);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Ctrl+")]
+    #[case("Hyper+O")]
+    #[case("Ctrl+Banana")]
+    fn test_invalid_shortcuts(#[case] shortcut: &str) {
+        assert!(shortcut.parse::<Shortcut>().is_err());
+    }
+
+    #[rstest]
+    #[case("ctrl+shift+z", "Ctrl+Shift+Z")]
+    #[case("Shift + F3", "Shift+F3")]
+    #[case("alt+enter", "Alt+Enter")]
+    #[case("Ctrl++", "Ctrl++")]
+    fn test_display(#[case] shortcut: &str, #[case] expected: &str) {
+        let shortcut = shortcut.parse::<Shortcut>().unwrap();
+        assert_eq!(shortcut.to_string(), expected);
+        assert_eq!(expected.parse::<Shortcut>().unwrap(), shortcut);
+    }
+
+    #[rstest]
+    #[case(character("g"), Modifiers::empty(), true)]
+    #[case(character("G"), Modifiers::SHIFT, true)]
+    #[case(character("v"), Modifiers::COMMAND, true)]
+    #[case(character("z"), Modifiers::COMMAND, false)]
+    #[case(character("r"), Modifiers::ALT, false)]
+    #[case(Key::Named(Named::F3), Modifiers::SHIFT, false)]
+    #[case(Key::Named(Named::F5), Modifiers::empty(), false)]
+    #[case(Key::Named(Named::Enter), Modifiers::empty(), true)]
+    #[case(Key::Named(Named::Enter), Modifiers::COMMAND, false)]
+    #[case(Key::Named(Named::ArrowLeft), Modifiers::COMMAND, true)]
+    fn test_is_text_input_key(
+        #[case] key: Key,
+        #[case] modifiers: Modifiers,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(is_text_input_key(&key, modifiers), expected);
+    }
+
+    #[test]
+    fn test_remap() {
+        let mut shortcuts = Shortcuts::default();
+        assert_eq!(
+            shortcuts.action_for(&Key::Named(Named::F3), Modifiers::empty()),
+            Some(ShortcutAction::FindNext)
+        );
+
+        let overrides = BTreeMap::from([
+            ("find_next".to_owned(), "Ctrl+G".parse().unwrap()),
+            ("fly".to_owned(), "Ctrl+F".parse().unwrap()),
+        ]);
+        assert_eq!(shortcuts.remap(&overrides), ["fly"]);
+        assert_eq!(
+            shortcuts.action_for(&Key::Named(Named::F3), Modifiers::empty()),
+            None
+        );
+        assert_eq!(
+            shortcuts.action_for(&character("g"), Modifiers::COMMAND),
+            Some(ShortcutAction::FindNext)
+        );
+        assert_eq!(
+            shortcuts.action_for(&character("f"), Modifiers::COMMAND),
+            Some(ShortcutAction::FocusFind)
+        );
+    }
+}
@@ -1,143 +1,1798 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
 use iced::widget::text_editor;
+use regex::{Regex, RegexBuilder};
 
-use crate::{bit_set::BitSet, fs::FileSystemEntry};
+use crate::{
+    bit_set::BitSet,
+    fs::{EntryType, FileSystemEntry, Roots},
+};
 
-use super::{DisplayType, FilterOptions, Options, SortingOption};
+use super::{DisplayType, FilterInput, FilterOptions, Options, SortItem, SortOrder};
 
 #[derive(Debug, Default)]
 pub struct EditorState {
-    pub open_folder: Option<String>,
-    pub entries: Vec<FileSystemEntry>,
+    pub roots: Roots,
+    pub entries: Arc<Vec<FileSystemEntry>>,
     pub filtered_indices: Vec<usize>,
+    pub is_visible: BitSet,
 
     pub contents: text_editor::Content,
+    pub cursors: Vec<LineCursor>,
+    pub longest_line: usize,
+    /// The original path of the entry on each line, in the same display type as the lines.
+    pub original_lines: Vec<String>,
+    pub longest_original_line: usize,
+    /// What the highlighter needs to know about the entry on each line.
+    pub line_info: Arc<Vec<LineInfo>>,
     pub find_and_replace: FindAndReplace,
+
+    sorted: Option<SortedEntries>,
+    changed: BitSet,
+    /// How many entries have each new path, to find duplicate targets while editing.
+    target_counts: HashMap<String, usize>,
+    history: History,
+}
+
+/// The indices of all entries in a given order, so that changing which entries are visible
+/// doesn't require sorting them again.
+#[derive(Debug)]
+struct SortedEntries {
+    order: SortOrder,
+    display_type: DisplayType,
+    indices: Vec<usize>,
 }
 
 impl EditorState {
     pub fn handle_action(&mut self, action: text_editor::Action) {
+        let is_edit = matches!(action, text_editor::Action::Edit(_));
+        // Typing into a single entry is undone at once rather than character by character.
+        let typing_entry = match &action {
+            text_editor::Action::Edit(
+                text_editor::Edit::Insert(_)
+                | text_editor::Edit::Backspace
+                | text_editor::Edit::Delete,
+            ) if self.cursors.is_empty() && self.contents.selection().is_none() => {
+                self.cursor().entry
+            }
+            _ => None,
+        };
+        if !self.cursors.is_empty() {
+            self.handle_multi_cursor_action(action);
+        } else {
+            match action {
+                text_editor::Action::Edit(edit) => self.handle_edit(edit),
+                _ => self.contents.perform(action),
+            }
+        }
+        if is_edit {
+            self.history.finish_step(typing_entry);
+            self.update_occurences();
+            self.update_line_info();
+        }
+    }
+
+    /// Reverts the last step of edits. Steps are kept for every entry, so this also works for
+    /// entries that are currently filtered out.
+    pub fn undo(&mut self) {
+        if let Some(step) = self.history.undo.pop() {
+            for change in step.changes.iter().rev() {
+                self.write_path(change.entry, change.before.clone());
+            }
+            self.show_step(&step);
+            self.history.redo.push(step);
+            // Typing after an undo starts a new step rather than extending an older one.
+            if let Some(last) = self.history.undo.last_mut() {
+                last.typing_entry = None;
+            }
+        }
+    }
+
+    /// Makes the last undone step of edits again.
+    pub fn redo(&mut self) {
+        if let Some(mut step) = self.history.redo.pop() {
+            for change in &step.changes {
+                self.write_path(change.entry, change.after.clone());
+            }
+            self.show_step(&step);
+            step.typing_entry = None;
+            self.history.undo.push(step);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Renders the editor after undoing or redoing a step, with the cursor on the first entry
+    /// it changed if that is shown.
+    fn show_step(&mut self, step: &Step) {
+        self.cursors.clear();
+        let row = self.cursor().row;
+        self.render(EntryCursor {
+            entry: step.changes.first().map(|change| change.entry),
+            row,
+            col: usize::MAX,
+        });
+        self.update_occurences();
+    }
+
+    /// Finds all occurences of the find input in the shown lines.
+    pub fn update_occurences(&mut self) {
+        let find_and_replace = &mut self.find_and_replace;
+        find_and_replace.occurences.clear();
+        if let Some(regex) = find_and_replace.regex() {
+            for (row, line) in self.contents.lines().enumerate() {
+                find_and_replace.occurences.extend(
+                    regex
+                        .find_iter(&line)
+                        .filter(|m| !m.is_empty())
+                        .map(|m| FoundOccurence {
+                            line: row,
+                            start: m.start(),
+                            len: m.len(),
+                        }),
+                );
+            }
+        }
+        find_and_replace.current_occurence = find_and_replace
+            .current_occurence
+            .min(find_and_replace.occurences.len().saturating_sub(1));
+    }
+
+    /// Moves the cursor to the start of the next occurence after the cursor, or of the previous
+    /// one before it, wrapping around at the end.
+    pub fn find_next(&mut self, forward: bool) {
+        let occurences = &self.find_and_replace.occurences;
+        if occurences.is_empty() {
+            return;
+        }
+        let cursor = self.contents.cursor_position();
+        let next = if forward {
+            occurences
+                .iter()
+                .position(|occurence| (occurence.line, occurence.start) > cursor)
+                .unwrap_or(0)
+        } else {
+            occurences
+                .iter()
+                .rposition(|occurence| (occurence.line, occurence.start) < cursor)
+                .unwrap_or(occurences.len() - 1)
+        };
+        self.find_and_replace.current_occurence = next;
+        let occurence = &occurences[next];
+        self.cursors.clear();
+        self.contents = content_with_cursor(&self.contents.text(), occurence.line, occurence.start);
+    }
+
+    /// Replaces the current occurence, and moves on to the next one.
+    pub fn replace_current(&mut self) {
+        let Some(occurence) = self
+            .find_and_replace
+            .occurences
+            .get(self.find_and_replace.current_occurence)
+            .cloned()
+        else {
+            return;
+        };
+        let Some(regex) = self.find_and_replace.regex() else {
+            return;
+        };
+        let line = self
+            .contents
+            .line(occurence.line)
+            .expect("Occurences are only found in existing rows")
+            .to_owned();
+        // The occurence is only replaced if it still matches the same text.
+        let Some(captures) = regex
+            .captures_at(&line, occurence.start)
+            .filter(|captures| {
+                let matched = captures.get(0).expect("The whole match is always captured");
+                matched.start() == occurence.start && matched.len() == occurence.len
+            })
+        else {
+            return;
+        };
+        let mut replacement = String::new();
+        if self.find_and_replace.find.use_regex {
+            captures.expand(&self.find_and_replace.replace, &mut replacement);
+        } else {
+            replacement.push_str(&self.find_and_replace.replace);
+        }
+        let matched = captures.get(0).expect("The whole match is always captured");
+        let new_line = format!(
+            "{}{}{}",
+            &line[..matched.start()],
+            replacement,
+            &line[matched.end()..]
+        );
+        self.replace_lines([(occurence.line, new_line)]);
+        self.contents = content_with_cursor(
+            &self.contents.text(),
+            occurence.line,
+            matched.start() + replacement.len(),
+        );
+        self.find_next(true);
+    }
+
+    /// Replaces every occurence in the shown lines as a single step that can be undone.
+    pub fn replace_all(&mut self) {
+        let Some(regex) = self.find_and_replace.regex() else {
+            return;
+        };
+        let replace = &self.find_and_replace.replace;
+        let new_lines = self
+            .contents
+            .lines()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                let new_line = if self.find_and_replace.find.use_regex {
+                    regex.replace_all(&line, replace.as_str())
+                } else {
+                    regex.replace_all(&line, regex::NoExpand(replace))
+                };
+                match new_line {
+                    Cow::Owned(new_line) => Some((row, new_line)),
+                    Cow::Borrowed(_) => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        self.replace_lines(new_lines);
+    }
+
+    /// Sets the text of whole lines as one step. Nothing is replaced if a line would get a
+    /// separator while only names are shown.
+    fn replace_lines(&mut self, lines: impl IntoIterator<Item = (usize, String)>) {
+        let lines = lines.into_iter().collect::<Vec<_>>();
+        let display_type = self.display_type();
+        if matches!(display_type, DisplayType::JustName)
+            && lines.iter().any(|(_, line)| line.contains('/'))
+        {
+            return;
+        }
+        for (row, line) in lines {
+            let i = self.filtered_indices[row];
+            let new_path = self.path_from_line(i, &line, display_type);
+            self.set_path(i, new_path);
+        }
+        self.history.finish_step(None);
+        self.cursors.clear();
+        self.render(self.cursor());
+        self.update_occurences();
+    }
+
+    pub fn handle_cursor_action(&mut self, action: CursorAction) {
+        let Some(last_row) = self.filtered_indices.len().checked_sub(1) else {
+            return;
+        };
+        if self.cursors.is_empty() {
+            let (row, col) = self.contents.cursor_position();
+            let column = self.char_column(row, col);
+            self.cursors.push(LineCursor::new(row, column));
+        }
         match action {
-            text_editor::Action::Edit(edit) if !self.handle_edit(&edit) => {}
-            _ => self.contents.perform(action),
+            CursorAction::Add(row, column) => {
+                let row = row.min(last_row);
+                let cursor = LineCursor::new(row, column.min(self.line_length(row)));
+                if !self.cursors.contains(&cursor) {
+                    self.cursors.push(cursor);
+                }
+            }
+            CursorAction::SelectColumns { from, to } => {
+                let (first, last) = (from.0.min(to.0), from.0.max(to.0).min(last_row));
+                self.cursors = (first..=last)
+                    .map(|row| {
+                        let length = self.line_length(row);
+                        LineCursor {
+                            row,
+                            anchor: from.1.min(length),
+                            column: to.1.min(length),
+                        }
+                    })
+                    .collect();
+            }
+            CursorAction::Extend(motion) => {
+                let last = *self.cursors.last().expect("There is at least one cursor");
+                let row = match motion {
+                    text_editor::Motion::Up => last.row.checked_sub(1),
+                    text_editor::Motion::Down => Some(last.row + 1).filter(|row| *row <= last_row),
+                    _ => return self.move_cursors(motion, true),
+                };
+                if let Some(row) = row {
+                    let length = self.line_length(row);
+                    let cursor = LineCursor {
+                        row,
+                        anchor: last.anchor.min(length),
+                        column: last.column.min(length),
+                    };
+                    self.cursors.retain(|other| other.row != row);
+                    self.cursors.push(cursor);
+                }
+            }
         }
     }
 
-    fn handle_edit(&mut self, edit: &text_editor::Edit) -> bool {
-        let (row, col) = self.contents.cursor_position();
-        match edit {
-            text_editor::Edit::Insert(_) => true,
-            text_editor::Edit::Paste(_) => true,
-            text_editor::Edit::Backspace => col > 0,
-            text_editor::Edit::Delete => {
-                col < self
-                    .contents
-                    .line(row)
-                    .expect(
-                        "The contents cursor position cannot point to a row that does not exist",
-                    )
-                    .len()
+    /// Applies editor actions to every cursor. Horizontal motions move or extend the selection
+    /// of each cursor and edits are made at every cursor, while any other action goes back to a
+    /// single cursor.
+    fn handle_multi_cursor_action(&mut self, action: text_editor::Action) {
+        let is_horizontal = |motion: &text_editor::Motion| {
+            matches!(
+                motion,
+                text_editor::Motion::Left
+                    | text_editor::Motion::Right
+                    | text_editor::Motion::Home
+                    | text_editor::Motion::End
+            )
+        };
+        match action {
+            text_editor::Action::Edit(edit) => self.edit_at_cursors(edit),
+            text_editor::Action::Move(motion) if is_horizontal(&motion) => {
+                self.move_cursors(motion, false)
+            }
+            text_editor::Action::Select(motion) if is_horizontal(&motion) => {
+                self.move_cursors(motion, true)
+            }
+            _ => {
+                self.cursors.clear();
+                self.contents.perform(action);
             }
-            text_editor::Edit::Enter => false,
         }
     }
 
-    pub fn show_filtered_entries(&mut self, options: &Options, filter_options: &FilterOptions) {
-        let visibility_vectors = filter_options.get_visibility_vectors();
-        let mut is_visible = visibility_vectors[0].chunks.clone();
-        for vector in visibility_vectors.iter().skip(1) {
-            is_visible
-                .iter_mut()
-                .zip(vector.chunks.iter())
-                .for_each(|(left, right)| *left &= right);
-        }
-        let is_visible = BitSet {
-            chunks: is_visible,
-            size: visibility_vectors[0].size,
-        };
-        let mut content_size = 0;
-        let (filtered, entries): (Vec<usize>, Vec<&str>) = self
+    fn move_cursors(&mut self, motion: text_editor::Motion, is_selecting: bool) {
+        for i in 0..self.cursors.len() {
+            let length = self.line_length(self.cursors[i].row);
+            let cursor = &mut self.cursors[i];
+            let (start, end) = cursor.range();
+            cursor.column = match motion {
+                text_editor::Motion::Left if !is_selecting && start != end => start,
+                text_editor::Motion::Right if !is_selecting && start != end => end,
+                text_editor::Motion::Left => cursor.column.saturating_sub(1),
+                text_editor::Motion::Right => (cursor.column + 1).min(length),
+                text_editor::Motion::Home => 0,
+                text_editor::Motion::End => length,
+                _ => cursor.column,
+            };
+            if !is_selecting {
+                cursor.anchor = cursor.column;
+            }
+        }
+    }
+
+    /// Makes the same edit at every cursor. A pasted text with as many lines as there are
+    /// cursors is split up, with each cursor getting one line in the order they are shown.
+    fn edit_at_cursors(&mut self, edit: text_editor::Edit) {
+        let allows_separators = !matches!(self.display_type(), DisplayType::JustName);
+        let text = match &edit {
+            text_editor::Edit::Enter | text_editor::Edit::Insert('\n' | '\r') => return,
+            text_editor::Edit::Insert(c) => Some(c.to_string()),
+            text_editor::Edit::Paste(text) => Some(
+                text.trim_end_matches('\n')
+                    .trim_end_matches('\r')
+                    .to_owned(),
+            ),
+            text_editor::Edit::Backspace | text_editor::Edit::Delete => None,
+        };
+        if !allows_separators && text.as_ref().is_some_and(|text| text.contains('/')) {
+            return;
+        }
+        let texts = match &text {
+            Some(text) if text.contains('\n') => {
+                let lines = text
+                    .split('\n')
+                    .map(|line| line.trim_end_matches('\r'))
+                    .collect::<Vec<_>>();
+                if lines.len() != self.cursors.len() {
+                    return;
+                }
+                lines
+            }
+            Some(text) => vec![text.as_str(); self.cursors.len()],
+            None => Vec::new(),
+        };
+
+        self.cursors
+            .sort_by_key(|cursor| (cursor.row, cursor.range()));
+        self.cursors.dedup();
+        let display_type = self.display_type();
+        let mut first = 0;
+        while first < self.cursors.len() {
+            let row = self.cursors[first].row;
+            let count = self.cursors[first..]
+                .iter()
+                .take_while(|cursor| cursor.row == row)
+                .count();
+            let line = self
+                .contents
+                .line(row)
+                .expect("Cursors are only placed on existing rows")
+                .to_owned();
+            let texts = texts.get(first..first + count).unwrap_or_default();
+            let new_line =
+                edit_line_at_cursors(&line, &mut self.cursors[first..first + count], &edit, texts);
+            let i = self.filtered_indices[row];
+            let new_path = self.path_from_line(i, &new_line, display_type);
+            self.set_path(i, new_path);
+            first += count;
+        }
+
+        let last = *self.cursors.last().expect("There is at least one cursor");
+        let i = self.filtered_indices[last.row];
+        let mut line = String::new();
+        self.push_line(&mut line, &self.entries[i].path, display_type);
+        self.render(EntryCursor {
+            entry: Some(i),
+            row: last.row,
+            col: byte_index(&line, last.column),
+        });
+    }
+
+    fn line_length(&self, row: usize) -> usize {
+        self.contents
+            .line(row)
+            .map_or(0, |line| line.chars().count())
+    }
+
+    fn char_column(&self, row: usize, byte_column: usize) -> usize {
+        self.contents.line(row).map_or(0, |line| {
+            line[..byte_column.min(line.len())].chars().count()
+        })
+    }
+
+    /// Stores the current text of the given line as the new path of the entry shown there.
+    fn store_line_edit(&mut self, row: usize) {
+        let (Some(i), Some(line)) = (self.filtered_indices.get(row), self.contents.line(row))
+        else {
+            return;
+        };
+        let i = *i;
+        let new_path = self.path_from_line(i, &line, self.display_type());
+        self.longest_line = self.longest_line.max(line.chars().count());
+        drop(line);
+        self.set_path(i, new_path);
+    }
+
+    /// Maps a line as it is shown for the given display type back to a relative path.
+    ///
+    /// - With [`DisplayType::JustName`], the line replaces the final component of the entry's
+    ///   current path. Separators can't be typed in this mode, so the entry stays in its folder.
+    /// - With [`DisplayType::RelativePath`], the line is the whole path relative to the opened
+    ///   folder. Changing the folder part moves the entry, and `..` components can point outside
+    ///   of the opened folder.
+    /// - With [`DisplayType::AbsolutePath`], the line is made relative to the opened folder, so
+    ///   the result is the same as editing the relative path.
+    fn path_from_line(&self, i: usize, line: &str, display_type: DisplayType) -> String {
+        match display_type {
+            DisplayType::AbsolutePath => self.roots.relative(line, self.entries[i].root),
+            DisplayType::RelativePath => self.roots.normalize(line),
+            DisplayType::JustName => {
+                let entry = &self.entries[i];
+                format!("{}{}", &entry.path[..=entry.last_sep], line)
+            }
+        }
+    }
+
+    /// Sets the new path of an entry, recording the change in the history.
+    fn set_path(&mut self, i: usize, path: String) {
+        if self.entries[i].path != path {
+            self.history
+                .record(i, self.entries[i].path.clone(), path.clone());
+            self.write_path(i, path);
+        }
+    }
+
+    fn write_path(&mut self, i: usize, path: String) {
+        let entry = &mut Arc::make_mut(&mut self.entries)[i];
+        if let Some(count) = self.target_counts.get_mut(&entry.path) {
+            *count -= 1;
+            if *count == 0 {
+                self.target_counts.remove(&entry.path);
+            }
+        }
+        *self.target_counts.entry(path.clone()).or_default() += 1;
+        entry.set_path(path);
+        self.changed.set_bit(i, entry.is_changed());
+    }
+
+    /// Resets the entry under the cursor to its original path.
+    pub fn reset_current_entry(&mut self) {
+        let (row, _) = self.contents.cursor_position();
+        if let Some(i) = self.filtered_indices.get(row).copied() {
+            self.set_path(i, self.entries[i].og_path.clone());
+            self.history.finish_step(None);
+            self.render(self.cursor());
+        }
+    }
+
+    /// Resets every entry, including those that are filtered out, to its original path.
+    pub fn reset_all_entries(&mut self) {
+        for i in self.changed.iter_ones().collect::<Vec<_>>() {
+            self.set_path(i, self.entries[i].og_path.clone());
+        }
+        self.history.finish_step(None);
+        self.render(self.cursor());
+    }
+
+    /// Sets the new path of every entry whose original path is a key of `edits`, as a single
+    /// step in the history. Returns the edits that don't belong to any entry.
+    pub fn apply_edits(&mut self, edits: &BTreeMap<String, String>) -> Vec<(String, String)> {
+        let indices = self
             .entries
             .iter()
             .enumerate()
-            .filter_map(|(i, entry)| {
-                if is_visible.is_bit_set(i) {
-                    let display_path = self.format_entry(
-                        entry,
-                        options
-                            .display_type
-                            .selected
-                            .unwrap_or(DisplayType::RelativePath),
-                    );
-                    content_size += display_path.len();
-                    Some((i, display_path))
-                } else {
-                    None
-                }
+            .map(|(i, entry)| (entry.og_path.as_str(), i))
+            .collect::<HashMap<_, _>>();
+        let mut matched = Vec::new();
+        let mut unmatched = Vec::new();
+        for (og_path, path) in edits {
+            match indices.get(og_path.as_str()) {
+                Some(i) => matched.push((*i, path.clone())),
+                None => unmatched.push((og_path.clone(), path.clone())),
+            }
+        }
+        for (i, path) in matched {
+            self.set_path(i, path);
+        }
+        self.history.finish_step(None);
+        unmatched
+    }
+
+    /// Returns the amount of entries with a pending new path, and how many of those are
+    /// currently filtered out.
+    pub fn pending_edit_count(&self) -> (usize, usize) {
+        let mut hidden = self.changed.clone();
+        hidden.and_not(&self.is_visible);
+        (self.changed.count_ones(), hidden.count_ones())
+    }
+
+    fn display_type(&self) -> DisplayType {
+        self.sorted
+            .as_ref()
+            .map(|sorted| sorted.display_type)
+            .unwrap_or(DisplayType::RelativePath)
+    }
+
+    /// Performs an edit without ever adding or removing a line, as every line belongs to an
+    /// entry.
+    ///
+    /// Edits without a selection only touch the line the cursor is on and are performed as is.
+    /// Edits of a selection are turned into an edit of every selected line, see [`edit_lines`].
+    /// Edits that can't be done without changing the line count are dropped.
+    fn handle_edit(&mut self, edit: text_editor::Edit) {
+        let edit = match edit {
+            text_editor::Edit::Paste(text) if text.ends_with('\n') => {
+                let text = text.trim_end_matches('\n').trim_end_matches('\r');
+                text_editor::Edit::Paste(Arc::new(text.to_owned()))
+            }
+            edit => edit,
+        };
+        let allows_separators = !matches!(self.display_type(), DisplayType::JustName);
+        let adds_separator = match &edit {
+            text_editor::Edit::Insert(c) => *c == '/',
+            text_editor::Edit::Paste(text) => text.contains('/'),
+            _ => false,
+        };
+        if adds_separator && !allows_separators {
+            return;
+        }
+
+        let selection = self.contents.selection().filter(|text| !text.is_empty());
+        if selection.is_some() {
+            // Moving left collapses the selection to its start, which isn't exposed otherwise.
+            self.contents
+                .perform(text_editor::Action::Move(text_editor::Motion::Left));
+        }
+        let (row, start) = self.contents.cursor_position();
+        let (line_count, end) = match &selection {
+            Some(text) => match text.rfind('\n') {
+                Some(last_break) => (text.matches('\n').count() + 1, text.len() - last_break - 1),
+                None => (1, start + text.len()),
+            },
+            None => (1, start),
+        };
+        if row + line_count > self.filtered_indices.len() {
+            return;
+        }
+        let lines = (row..row + line_count)
+            .map(|row| {
+                self.contents
+                    .line(row)
+                    .expect("The selection only contains existing rows")
+                    .to_owned()
             })
-            .unzip();
-        let mut filtered = filtered.into_iter().enumerate().collect::<Vec<_>>();
-        match options.sorting.selected {
-            Some(SortingOption::SortAscendingCaseInsensitive) => {
-                filtered.sort_unstable_by_key(|(_, i)| entries[*i].to_ascii_lowercase())
-            }
-            Some(SortingOption::SortAscendingCaseSensitive) => {
-                filtered.sort_unstable_by_key(|(_, i)| entries[*i])
-            }
-            Some(SortingOption::SortDescendingCaseInsensitive) => filtered
-                .sort_unstable_by_key(|(_, i)| std::cmp::Reverse(entries[*i].to_ascii_lowercase())),
-            Some(SortingOption::SortDescendingCaseSensitive) => {
-                filtered.sort_unstable_by_key(|(_, i)| std::cmp::Reverse(entries[*i]))
-            }
-            _ => {}
-        }
-        let (sort_indices, filtered): (Vec<usize>, Vec<usize>) = filtered.into_iter().unzip();
-        self.filtered_indices = filtered;
-        let mut content = String::with_capacity(content_size);
-        let absolute_prefix = if matches!(
-            options
-                .display_type
-                .selected
-                .expect("There must be a display type"),
-            DisplayType::AbsolutePath
-        ) {
-            self.open_folder
-                .as_ref()
-                .expect("A folder must have been opened to get here")
-                .to_owned()
-        } else {
-            String::new()
+            .collect::<Vec<_>>();
+        let lines = lines.iter().map(String::as_str).collect::<Vec<_>>();
+        let Some(line_edit) = edit_lines(&lines, start, end, &edit) else {
+            return;
         };
-        sort_indices.into_iter().for_each(|i| {
-            content.push_str(&absolute_prefix);
-            content.push_str(entries[i]);
-            content.push('\n');
+
+        if selection.is_none() {
+            self.contents.perform(text_editor::Action::Edit(edit));
+            self.store_line_edit(row);
+            return;
+        }
+        let display_type = self.display_type();
+        for (offset, line) in line_edit.lines.iter().enumerate() {
+            let i = self.filtered_indices[row + offset];
+            let new_path = self.path_from_line(i, line, display_type);
+            self.set_path(i, new_path);
+        }
+        let (cursor_offset, col) = line_edit.cursor;
+        self.render(EntryCursor {
+            entry: Some(self.filtered_indices[row + cursor_offset]),
+            row: row + cursor_offset,
+            col,
         });
-        self.contents = text_editor::Content::with_text(&content);
     }
 
-    fn format_entry<'a>(
-        &'_ self,
-        entry: &'a FileSystemEntry,
-        display_type: DisplayType,
-    ) -> &'a str {
+    pub fn open(&mut self, roots: Roots, entries: Vec<FileSystemEntry>) {
+        self.roots = roots;
+        self.entries = Arc::new(entries);
+        self.count_targets();
+        self.filtered_indices.clear();
+        self.is_visible = BitSet::new();
+        self.is_visible.resize(self.entries.len());
+        self.sorted = None;
+        self.contents = text_editor::Content::new();
+        self.cursors.clear();
+        self.longest_line = 0;
+        self.original_lines.clear();
+        self.longest_original_line = 0;
+        self.history = History::default();
+    }
+
+    /// Replaces the entries with a new scan of the same folders. Entries that still exist keep
+    /// their new path and their place in the history, and the editor keeps showing them until
+    /// the filters are updated for the new entries. Returns the edits of entries that are gone.
+    pub fn refresh(&mut self, mut entries: Vec<FileSystemEntry>) -> Vec<(String, String)> {
+        let new_indices = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.og_path.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let new_index = self
+            .entries
+            .iter()
+            .map(|entry| new_indices.get(&entry.og_path).copied())
+            .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for (entry, new_index) in self.entries.iter().zip(&new_index) {
+            match new_index {
+                Some(i) => entries[*i].set_path(entry.path.clone()),
+                None if entry.is_changed() => {
+                    removed.push((entry.og_path.clone(), entry.path.clone()))
+                }
+                None => {}
+            }
+        }
+
+        let mut cursor = self.cursor();
+        cursor.entry = cursor.entry.and_then(|i| new_index[i]);
+        self.history.remap(&new_index);
+        self.filtered_indices = self
+            .filtered_indices
+            .iter()
+            .filter_map(|i| new_index[*i])
+            .collect();
+        self.entries = Arc::new(entries);
+        self.count_targets();
+        self.cursors.clear();
+        self.render(cursor);
+        // The new entries are only sorted and filtered once the filters are updated.
+        self.is_visible = BitSet::new();
+        self.is_visible.resize(self.entries.len());
+        self.sorted = None;
+        removed
+    }
+
+    fn count_targets(&mut self) {
+        self.changed = BitSet::new();
+        self.changed.resize(self.entries.len());
+        self.target_counts.clear();
+        for (i, entry) in self.entries.iter().enumerate() {
+            self.changed.set_bit(i, entry.is_changed());
+            *self.target_counts.entry(entry.path.clone()).or_default() += 1;
+        }
+    }
+
+    /// Updates the editor to show the entries that pass all filters in the selected order.
+    /// With [`Options::show_only_changed`], only the entries that have a pending new path at
+    /// this point are shown, so lines don't disappear while they are being edited.
+    ///
+    /// Lines are rendered from the new path of each entry, so edits are kept no matter how the
+    /// entries are filtered, sorted or displayed. The cursor stays on the entry it was on if that entry is
+    /// still visible. If neither the visible entries nor the order changed, the editor is left
+    /// untouched.
+    pub fn show_filtered_entries(&mut self, options: &Options, filter_options: &FilterOptions) {
+        let mut is_visible = BitSet::new();
+        is_visible.resize(self.entries.len());
+        is_visible.set_all();
+        for vector in filter_options.get_visibility_vectors() {
+            is_visible.and(vector);
+        }
+        if options.show_only_changed {
+            is_visible.and(&self.changed);
+        }
+        let display_type = options
+            .display_type
+            .selected
+            .unwrap_or(DisplayType::RelativePath);
+        let sort_order = SortOrder::from_options(options);
+
+        let (is_same_order, is_same_display_type) = match &self.sorted {
+            Some(sorted) => (
+                sorted.order == sort_order,
+                sorted.display_type == display_type,
+            ),
+            None => (false, false),
+        };
+        if is_same_order && is_same_display_type && is_visible == self.is_visible {
+            return;
+        }
+
+        let cursor = self.cursor();
+        self.cursors.clear();
+        if !(is_same_order && is_same_display_type) {
+            self.sorted = Some(self.sort_entries(sort_order, display_type));
+        }
+        let sorted = self
+            .sorted
+            .as_ref()
+            .expect("The sorted entries were just computed");
+        self.filtered_indices = sorted
+            .indices
+            .iter()
+            .copied()
+            .filter(|i| is_visible.is_bit_set(*i))
+            .collect();
+        self.is_visible = is_visible;
+        self.render(cursor);
+        self.update_occurences();
+    }
+
+    /// Returns the cursor position and the entry on the line it is on.
+    fn cursor(&self) -> EntryCursor {
+        let (row, col) = self.contents.cursor_position();
+        EntryCursor {
+            entry: self.filtered_indices.get(row).copied(),
+            row,
+            col,
+        }
+    }
+
+    /// Rebuilds the editor contents from the filtered entries, placing the cursor on the same
+    /// entry as before if it is still shown, or on the same row otherwise.
+    fn render(&mut self, cursor: EntryCursor) {
+        let display_type = self.display_type();
+        let mut content = String::new();
+        let mut position = (cursor.row.min(self.filtered_indices.len()), 0);
+        let mut longest_line = 0;
+        let mut original_lines = Vec::with_capacity(self.filtered_indices.len());
+        for (row, i) in self.filtered_indices.iter().enumerate() {
+            let mut original = String::new();
+            self.push_line(&mut original, &self.entries[*i].og_path, display_type);
+            original_lines.push(original);
+
+            let line_start = content.len();
+            self.push_line(&mut content, &self.entries[*i].path, display_type);
+            let line = &content[line_start..];
+            longest_line = longest_line.max(line.chars().count());
+            if cursor.entry == Some(*i) {
+                position = (row, cursor.col.min(line.len()));
+            }
+            content.push('\n');
+        }
+        self.contents = content_with_cursor(&content, position.0, position.1);
+        self.longest_line = longest_line;
+        self.longest_original_line = original_lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.original_lines = original_lines;
+        self.update_line_info();
+        for cursor in &mut self.cursors {
+            let length = self
+                .contents
+                .line(cursor.row)
+                .map_or(0, |line| line.chars().count());
+            cursor.anchor = cursor.anchor.min(length);
+            cursor.column = cursor.column.min(length);
+        }
+    }
+
+    /// Updates the information about the entry on each line. It is only replaced if it changed,
+    /// as that makes the highlighter go over every line again.
+    fn update_line_info(&mut self) {
+        let line_info = self
+            .filtered_indices
+            .iter()
+            .map(|i| {
+                let entry = &self.entries[*i];
+                LineInfo {
+                    is_folder: matches!(entry.entry_type, EntryType::Folder),
+                    is_duplicate: self.target_counts.get(&entry.path).is_some_and(|n| *n > 1),
+                }
+            })
+            .collect::<Vec<_>>();
+        if *self.line_info != line_info {
+            self.line_info = Arc::new(line_info);
+        }
+    }
+
+    /// Appends the line for the given path in the given display type. A path that is in none of
+    /// the opened folders is shown as is in [`DisplayType::AbsolutePath`].
+    fn push_line(&self, content: &mut String, path: &str, display_type: DisplayType) {
+        match display_type {
+            DisplayType::AbsolutePath => match self.roots.absolute(path) {
+                Some(path) => content.push_str(&path),
+                None => content.push_str(path),
+            },
+            _ => content.push_str(Self::format_path(path, display_type)),
+        }
+    }
+
+    fn sort_entries(&self, order: SortOrder, display_type: DisplayType) -> SortedEntries {
+        let mut indices = (0..self.entries.len()).collect::<Vec<_>>();
+        if !order.is_unsorted() {
+            indices.sort_by(|a, b| {
+                let a = &self.entries[*a];
+                let b = &self.entries[*b];
+                order.compare(
+                    &SortItem {
+                        entry: a,
+                        display: Self::format_entry(a, display_type),
+                    },
+                    &SortItem {
+                        entry: b,
+                        display: Self::format_entry(b, display_type),
+                    },
+                )
+            });
+        }
+        SortedEntries {
+            order,
+            display_type,
+            indices,
+        }
+    }
+
+    fn format_entry(entry: &FileSystemEntry, display_type: DisplayType) -> &str {
         match display_type {
-            DisplayType::AbsolutePath | DisplayType::RelativePath => &entry.path,
-            DisplayType::JustName => &entry.path[entry.last_sep + 1..],
+            DisplayType::AbsolutePath | DisplayType::RelativePath => &entry.og_path,
+            DisplayType::JustName => entry.og_name(),
+        }
+    }
+
+    fn format_path(path: &str, display_type: DisplayType) -> &str {
+        match display_type {
+            DisplayType::AbsolutePath | DisplayType::RelativePath => path,
+            DisplayType::JustName => {
+                &path[path.rfind('/').expect("All paths contain a separator") + 1..]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineInfo {
+    pub is_folder: bool,
+    /// Whether another entry has the same new path.
+    pub is_duplicate: bool,
+}
+
+/// One of the cursors of a multi-cursor edit, selecting the characters between `anchor` and
+/// `column` of a row. Columns count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCursor {
+    pub row: usize,
+    pub anchor: usize,
+    pub column: usize,
+}
+
+impl LineCursor {
+    fn new(row: usize, column: usize) -> Self {
+        Self {
+            row,
+            anchor: column,
+            column,
+        }
+    }
+
+    /// The start and end column of the selection.
+    pub fn range(&self) -> (usize, usize) {
+        (self.anchor.min(self.column), self.anchor.max(self.column))
+    }
+}
+
+/// Changes to the cursors that the text editor itself has no actions for.
+#[derive(Debug, Clone, Copy)]
+pub enum CursorAction {
+    /// Adds a cursor at a row and column.
+    Add(usize, usize),
+    /// Replaces all cursors with a column selection between two rows and columns.
+    SelectColumns {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// Extends the column selection by a row up or down, or extends the selection of every
+    /// cursor by a character left or right.
+    Extend(text_editor::Motion),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntryCursor {
+    entry: Option<usize>,
+    row: usize,
+    col: usize,
+}
+
+/// The new text of every line an edit touches, and where the cursor ends up relative to the first
+/// of those lines.
+#[derive(Debug, PartialEq, Eq)]
+struct LineEdit {
+    lines: Vec<String>,
+    cursor: (usize, usize),
+}
+
+/// Applies an edit to `lines`, where the selection goes from byte `start` of the first line to
+/// byte `end` of the last line. Without a selection, there is a single line and `start` and `end`
+/// are both the cursor column.
+///
+/// Deleting a selection empties the selected part of every line instead of joining them.
+/// Inserting a character or pasting a single line replaces the selection the same way and puts
+/// the text where the selection started, while pasting as many lines as are selected replaces
+/// the selected part of each line with the matching pasted line. Returns `None` for edits that
+/// would add or remove a line.
+fn edit_lines(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    edit: &text_editor::Edit,
+) -> Option<LineEdit> {
+    let line = lines[0];
+    let has_selection = lines.len() > 1 || start != end;
+    match edit {
+        text_editor::Edit::Enter => None,
+        text_editor::Edit::Insert('\n' | '\r') => None,
+        text_editor::Edit::Insert(c) => {
+            Some(replace_selection(lines, start, end, &[&c.to_string()]))
+        }
+        text_editor::Edit::Paste(text) => {
+            let pasted = text
+                .split('\n')
+                .map(|line| line.trim_end_matches('\r'))
+                .collect::<Vec<_>>();
+            (pasted.len() == 1 || pasted.len() == lines.len())
+                .then(|| replace_selection(lines, start, end, &pasted))
+        }
+        _ if has_selection => Some(replace_selection(lines, start, end, &[""])),
+        text_editor::Edit::Backspace => {
+            let previous = line[..start].chars().next_back()?;
+            Some(replace_selection(
+                lines,
+                start - previous.len_utf8(),
+                end,
+                &[""],
+            ))
+        }
+        text_editor::Edit::Delete => {
+            let next = line[start..].chars().next()?;
+            Some(replace_selection(
+                lines,
+                start,
+                end + next.len_utf8(),
+                &[""],
+            ))
+        }
+    }
+}
+
+/// Replaces the selected part of every line with the replacement for that line. A single
+/// replacement goes into the first line, and the selected parts of the other lines are emptied.
+fn replace_selection(lines: &[&str], start: usize, end: usize, replacements: &[&str]) -> LineEdit {
+    let last = lines.len() - 1;
+    let new_lines = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { &line[..start] } else { "" };
+            let suffix = if i == last { &line[end..] } else { "" };
+            let replacement = replacements.get(i).copied().unwrap_or_default();
+            format!("{prefix}{replacement}{suffix}")
+        })
+        .collect();
+    let cursor_row = replacements.len() - 1;
+    let cursor_start = if cursor_row == 0 { start } else { 0 };
+    LineEdit {
+        lines: new_lines,
+        cursor: (cursor_row, cursor_start + replacements[cursor_row].len()),
+    }
+}
+
+/// Makes an edit at every cursor of a line, with `cursors` sorted by column and not
+/// overlapping. `texts` has the text every cursor inserts in place of its selection, and is
+/// empty for backspace and delete, which remove the selection or else the character next to
+/// the cursor. The cursors are moved to where the edit leaves them.
+fn edit_line_at_cursors(
+    line: &str,
+    cursors: &mut [LineCursor],
+    edit: &text_editor::Edit,
+    texts: &[&str],
+) -> String {
+    let mut chars = line.chars().collect::<Vec<_>>();
+    let mut shift = 0isize;
+    for (i, cursor) in cursors.iter_mut().enumerate() {
+        let (start, end) = cursor.range();
+        let start = start.saturating_add_signed(shift).min(chars.len());
+        let end = end.saturating_add_signed(shift).min(chars.len());
+        let (start, end, text) = match (edit, texts.get(i)) {
+            (_, Some(text)) => (start, end, *text),
+            (text_editor::Edit::Backspace, None) if start == end => {
+                (start.saturating_sub(1), end, "")
+            }
+            (text_editor::Edit::Delete, None) if start == end => {
+                (start, (end + 1).min(chars.len()), "")
+            }
+            _ => (start, end, ""),
+        };
+        let inserted = text.chars().count();
+        chars.splice(start..end, text.chars());
+        shift += inserted as isize - (end - start) as isize;
+        *cursor = LineCursor::new(cursor.row, start + inserted);
+    }
+    chars.into_iter().collect()
+}
+
+/// The byte index of the character at `column`, or the length of the line if it is shorter.
+fn byte_index(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column)
+        .map_or(line.len(), |(i, _)| i)
+}
+
+/// Creates content with the cursor placed at the given line and byte column. Content that
+/// hasn't been laid out yet can't perform motions, so the cursor is moved there by pasting the
+/// text in front of it instead.
+fn content_with_cursor(text: &str, line: usize, column: usize) -> text_editor::Content {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(line)
+        .map(|line| line.len())
+        .sum::<usize>();
+    let mut offset = (line_start + column).min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let (before, after) = text.split_at(offset);
+    let mut content = text_editor::Content::with_text(after);
+    if !before.is_empty() {
+        content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(before.to_owned()),
+        )));
+    }
+    content
+}
+
+/// Path changes that can be undone and redone. Changes are collected while an action runs and
+/// then stored as one step, so that a whole paste or replace is undone at once.
+#[derive(Debug, Default)]
+struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    pending: Vec<PathChange>,
+}
+
+#[derive(Debug)]
+struct Step {
+    changes: Vec<PathChange>,
+    typing_entry: Option<usize>,
+}
+
+#[derive(Debug)]
+struct PathChange {
+    entry: usize,
+    before: String,
+    after: String,
+}
+
+impl History {
+    const MAX_STEPS: usize = 1000;
+
+    fn record(&mut self, entry: usize, before: String, after: String) {
+        self.pending.push(PathChange {
+            entry,
+            before,
+            after,
+        });
+    }
+
+    /// Moves the changes to the new index of their entry after a rescan, where `new_index` maps
+    /// every old index to the new one. Changes to entries that are gone are dropped.
+    fn remap(&mut self, new_index: &[Option<usize>]) {
+        for steps in [&mut self.undo, &mut self.redo] {
+            for step in steps.iter_mut() {
+                step.changes
+                    .retain_mut(|change| match new_index[change.entry] {
+                        Some(i) => {
+                            change.entry = i;
+                            true
+                        }
+                        None => false,
+                    });
+                step.typing_entry = step.typing_entry.and_then(|i| new_index[i]);
+            }
+            steps.retain(|step| !step.changes.is_empty());
+        }
+    }
+
+    /// Stores the recorded changes as a step. Typing into the same entry as the last step
+    /// extends that step instead.
+    fn finish_step(&mut self, typing_entry: Option<usize>) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        let changes = std::mem::take(&mut self.pending);
+        match self.undo.last_mut() {
+            Some(step) if typing_entry.is_some() && step.typing_entry == typing_entry => {
+                for change in changes {
+                    match step.changes.iter_mut().find(|c| c.entry == change.entry) {
+                        Some(existing) => existing.after = change.after,
+                        None => step.changes.push(change),
+                    }
+                }
+            }
+            _ => {
+                if self.undo.len() == Self::MAX_STEPS {
+                    self.undo.remove(0);
+                }
+                self.undo.push(Step {
+                    changes,
+                    typing_entry,
+                });
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct FindAndReplace {
-    pub find: String,
+    pub find: FilterInput,
     pub replace: String,
     pub current_occurence: usize,
     pub occurences: Vec<FoundOccurence>,
 }
 
+impl FindAndReplace {
+    /// The regex occurences are found with. Plain text is escaped, and an invalid regex finds
+    /// nothing.
+    fn regex(&self) -> Option<Regex> {
+        if self.find.input.is_empty() {
+            return None;
+        }
+        if self.find.use_regex {
+            return match &self.find.regex {
+                Some(Ok(regex)) => Some(regex.clone()),
+                _ => None,
+            };
+        }
+        RegexBuilder::new(&regex::escape(&self.find.input))
+            .case_insensitive(!self.find.case_sensitive)
+            .build()
+            .ok()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FoundOccurence {
     pub line: usize,
     pub start: usize,
     pub len: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    use crate::{
+        apply,
+        config::FiltersConfig,
+        fs::EntryType,
+        state::{FilterKind, SortingOption},
+    };
+
+    fn entry(path: &str) -> FileSystemEntry {
+        FileSystemEntry::for_test(path, path, EntryType::File)
+    }
+
+    fn editor_state(paths: &[&str]) -> EditorState {
+        let mut state = EditorState::default();
+        state.open(
+            Roots::new(["/root".to_owned()]),
+            paths.iter().map(|path| entry(path)).collect(),
+        );
+        state
+    }
+
+    fn filter(filters: &mut FilterOptions, state: &EditorState, input: &str) {
+        filters.filter_input.state.input = input.to_owned();
+        let result = filters.start_job(&FilterKind::ALL, &state.entries).run();
+        assert!(filters.apply_job_result(result));
+    }
+
+    fn lines(state: &EditorState) -> Vec<String> {
+        state.contents.lines().map(|line| line.to_owned()).collect()
+    }
+
+    fn move_cursor(state: &mut EditorState, row: usize) {
+        state.contents = content_with_cursor(&state.contents.text(), row, 0);
+    }
+
+    fn replace_line(state: &mut EditorState, text: &str) {
+        state.handle_action(text_editor::Action::SelectLine);
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(text.to_owned()),
+        )));
+    }
+
+    #[test]
+    fn test_sorting_and_filtering() {
+        let mut state = editor_state(&["/file10", "/file2", "/other", "/file1"]);
+        let mut options = Options::default();
+        options.sorting.selected = Some(SortingOption::SortNaturalAscending);
+        let mut filters = FilterOptions::default();
+
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/file1", "/file2", "/file10", "/other"]);
+        assert_eq!(state.filtered_indices, [3, 1, 0, 2]);
+
+        filter(&mut filters, &state, "file");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/file1", "/file2", "/file10"]);
+        assert_eq!(state.is_visible.count_ones(), 3);
+    }
+
+    #[test]
+    fn test_edits_and_cursor_survive_filter_changes() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/renamed");
+        assert_eq!(state.contents.cursor_position(), (1, 8));
+
+        filters.filter_input.state.use_regex = true;
+        filter(&mut filters, &state, "b|c");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/renamed", "/c"]);
+        assert_eq!(state.contents.cursor_position(), (0, 8));
+
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/a", "/renamed", "/c"]);
+    }
+
+    #[test]
+    fn test_pending_edits_survive_hiding_and_display_type_changes() {
+        let mut state = editor_state(&["/dir/a", "/dir/b", "/c"]);
+        let mut options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        replace_line(&mut state, "/dir/x");
+        assert_eq!(state.pending_edit_count(), (1, 0));
+
+        filter(&mut filters, &state, "c");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/c"]);
+        assert_eq!(state.pending_edit_count(), (1, 1));
+
+        filter(&mut filters, &state, "");
+        options.display_type.selected = Some(DisplayType::JustName);
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["x", "b", "c"]);
+
+        move_cursor(&mut state, 0);
+        replace_line(&mut state, "y");
+        assert_eq!(state.entries[0].path, "/dir/y");
+        assert_eq!(state.entries[0].last_sep, 4);
+
+        options.display_type.selected = Some(DisplayType::AbsolutePath);
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/root/dir/y", "/root/dir/b", "/root/c"]);
+
+        move_cursor(&mut state, 0);
+        replace_line(&mut state, "/root/dir/a");
+        assert_eq!(state.pending_edit_count(), (0, 0));
+
+        replace_line(&mut state, "/elsewhere/a");
+        assert_eq!(state.entries[0].path, "/../elsewhere/a");
+        state.render(state.cursor());
+        assert_eq!(lines(&state)[0], "/elsewhere/a");
+    }
+
+    #[test]
+    fn test_moves_out_of_one_of_several_folders() {
+        let mut state = EditorState::default();
+        let mut b = entry("/drive/b.jpg");
+        b.root = 1;
+        state.open(
+            Roots::new(["/home/u/Downloads".to_owned(), "/mnt/drive".to_owned()]),
+            vec![entry("/Downloads/a.jpg"), b],
+        );
+        let mut options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        replace_line(&mut state, "/Downloads/x/../../a.jpg");
+        assert_eq!(state.entries[0].path, "/Downloads/../a.jpg");
+
+        options.display_type.selected = Some(DisplayType::AbsolutePath);
+        state.show_filtered_entries(&options, &filters);
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/mnt/b.jpg");
+        assert_eq!(state.entries[1].path, "/drive/../b.jpg");
+        state.render(state.cursor());
+        assert_eq!(lines(&state), ["/home/u/a.jpg", "/mnt/b.jpg"]);
+
+        let errors = apply::plan(&state.roots, &state.entries, false).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        let operations = apply::plan(&state.roots, &state.entries, true)
+            .unwrap()
+            .operations;
+        assert_eq!(
+            operations,
+            [
+                apply::Operation::Rename {
+                    from: "/home/u/Downloads/a.jpg".to_owned(),
+                    to: "/home/u/a.jpg".to_owned(),
+                },
+                apply::Operation::Rename {
+                    from: "/mnt/drive/b.jpg".to_owned(),
+                    to: "/mnt/b.jpg".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moves_and_resets() {
+        let mut state = editor_state(&["/dir/a", "/b", "/c"]);
+        let mut options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/dir/sub/b");
+        move_cursor(&mut state, 2);
+        replace_line(&mut state, "c2/");
+        assert_eq!(state.entries[1].path, "/dir/sub/b");
+        assert_eq!(state.entries[1].last_sep, 8);
+        assert_eq!(state.entries[2].path, "/c2/");
+
+        options.display_type.selected = Some(DisplayType::JustName);
+        state.show_filtered_entries(&options, &filters);
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "x");
+        assert_eq!(state.entries[1].path, "/dir/sub/x");
+
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Insert('/')));
+        assert_eq!(state.entries[1].path, "/dir/sub/x");
+
+        state.reset_current_entry();
+        assert_eq!(state.entries[1].path, "/b");
+        assert_eq!(lines(&state), ["a", "b", ""]);
+        assert_eq!(state.pending_edit_count(), (1, 0));
+
+        state.reset_all_entries();
+        assert_eq!(lines(&state), ["a", "b", "c"]);
+        assert_eq!(state.pending_edit_count(), (0, 0));
+    }
+
+    fn paste(text: &str) -> text_editor::Edit {
+        text_editor::Edit::Paste(Arc::new(text.to_owned()))
+    }
+
+    #[rstest]
+    #[case::insert(&["ab"], 1, 1, text_editor::Edit::Insert('x'), Some((&["axb"][..], (0, 2))))]
+    #[case::insert_newline(&["ab"], 1, 1, text_editor::Edit::Insert('\n'), None)]
+    #[case::insert_over_selection(&["abc", "def"], 1, 2, text_editor::Edit::Insert('x'), Some((&["ax", "f"][..], (0, 2))))]
+    #[case::paste(&["ab"], 1, 1, paste("xy"), Some((&["axyb"][..], (0, 3))))]
+    #[case::paste_lines(&["ab"], 1, 1, paste("x\ny"), None)]
+    #[case::paste_over_selection(&["abc", "def", "ghi"], 1, 1, paste("x"), Some((&["ax", "", "hi"][..], (0, 2))))]
+    #[case::paste_line_per_line(&["abc", "def"], 0, 3, paste("x\ny"), Some((&["x", "y"][..], (1, 1))))]
+    #[case::paste_keeps_unselected_parts(&["abc", "def"], 2, 1, paste("x\r\ny"), Some((&["abx", "yef"][..], (1, 1))))]
+    #[case::paste_line_count_mismatch(&["abc", "def"], 0, 3, paste("x\ny\nz"), None)]
+    #[case::enter(&["ab"], 1, 1, text_editor::Edit::Enter, None)]
+    #[case::enter_over_selection(&["abc", "def"], 1, 2, text_editor::Edit::Enter, None)]
+    #[case::backspace(&["aé"], 3, 3, text_editor::Edit::Backspace, Some((&["a"][..], (0, 1))))]
+    #[case::backspace_at_line_start(&["ab"], 0, 0, text_editor::Edit::Backspace, None)]
+    #[case::backspace_selection(&["abc", "def"], 1, 2, text_editor::Edit::Backspace, Some((&["a", "f"][..], (0, 1))))]
+    #[case::delete(&["éb"], 0, 0, text_editor::Edit::Delete, Some((&["b"][..], (0, 0))))]
+    #[case::delete_at_line_end(&["ab"], 2, 2, text_editor::Edit::Delete, None)]
+    #[case::delete_selection(&["abc", "def", "ghi"], 0, 3, text_editor::Edit::Delete, Some((&["", "", ""][..], (0, 0))))]
+    fn test_edit_lines(
+        #[case] lines: &[&str],
+        #[case] start: usize,
+        #[case] end: usize,
+        #[case] edit: text_editor::Edit,
+        #[case] expected: Option<(&[&str], (usize, usize))>,
+    ) {
+        let expected = expected.map(|(lines, cursor)| LineEdit {
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            cursor,
+        });
+        assert_eq!(edit_lines(lines, start, end, &edit), expected);
+    }
+
+    #[test]
+    fn test_multi_line_edits_keep_every_line() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        state.contents = content_with_cursor(&state.contents.text(), 0, 1);
+        state.handle_action(text_editor::Action::Select(
+            text_editor::Motion::DocumentEnd,
+        ));
+        state.handle_action(text_editor::Action::Edit(paste("x\ny\nz")));
+        assert_eq!(lines(&state), ["/x", "/y", "/z"]);
+        assert_eq!(state.entries[1].path, "/y");
+        assert_eq!(state.contents.cursor_position(), (2, 1));
+
+        state.contents = content_with_cursor(&state.contents.text(), 1, 0);
+        state.handle_action(text_editor::Action::Select(
+            text_editor::Motion::DocumentEnd,
+        ));
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Delete));
+        assert_eq!(lines(&state), ["/x", "/", "/"]);
+        assert_eq!(state.pending_edit_count(), (3, 0));
+
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Enter));
+        state.handle_action(text_editor::Action::Edit(paste("1\n2")));
+        assert_eq!(lines(&state), ["/x", "/", "/"]);
+    }
+
+    fn line_cursors(positions: &[(usize, usize)]) -> Vec<LineCursor> {
+        positions
+            .iter()
+            .map(|(anchor, column)| LineCursor {
+                row: 0,
+                anchor: *anchor,
+                column: *column,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::insert("abc", &[(0, 0), (2, 2)], text_editor::Edit::Insert('x'), &["x", "x"], "xabxc", &[1, 4])]
+    #[case::replace_selections("abcd", &[(0, 1), (3, 2)], text_editor::Edit::Insert('é'), &["é", "é"], "ébéd", &[1, 3])]
+    #[case::paste_per_cursor("a-b", &[(1, 1), (3, 3)], paste("x"), &["1", "22"], "a1-b22", &[2, 6])]
+    #[case::backspace("abcd", &[(1, 1), (3, 3)], text_editor::Edit::Backspace, &[], "bd", &[0, 1])]
+    #[case::backspace_at_start("ab", &[(0, 0), (2, 2)], text_editor::Edit::Backspace, &[], "a", &[0, 1])]
+    #[case::delete("abcd", &[(0, 0), (2, 2)], text_editor::Edit::Delete, &[], "bd", &[0, 1])]
+    #[case::delete_selection("abcd", &[(0, 2), (4, 3)], text_editor::Edit::Delete, &[], "c", &[0, 1])]
+    fn test_edit_line_at_cursors(
+        #[case] line: &str,
+        #[case] positions: &[(usize, usize)],
+        #[case] edit: text_editor::Edit,
+        #[case] texts: &[&str],
+        #[case] expected: &str,
+        #[case] columns: &[usize],
+    ) {
+        let mut cursors = line_cursors(positions);
+        assert_eq!(
+            edit_line_at_cursors(line, &mut cursors, &edit, texts),
+            expected
+        );
+        assert_eq!(
+            cursors,
+            line_cursors(
+                &columns
+                    .iter()
+                    .map(|column| (*column, *column))
+                    .collect::<Vec<_>>()
+            )
+        );
+    }
+
+    #[test]
+    fn test_multiple_cursors() {
+        let mut state = editor_state(&["/a1", "/b22", "/c333"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        state.handle_cursor_action(CursorAction::SelectColumns {
+            from: (0, 1),
+            to: (2, 2),
+        });
+        assert_eq!(state.cursors.len(), 3);
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Insert('x')));
+        assert_eq!(lines(&state), ["/x1", "/x22", "/x333"]);
+
+        state.handle_action(text_editor::Action::Select(text_editor::Motion::End));
+        state.handle_action(text_editor::Action::Edit(paste("1\n2\n3")));
+        assert_eq!(lines(&state), ["/x1", "/x2", "/x3"]);
+        assert_eq!(state.entries[2].path, "/x3");
+        assert_eq!(state.contents.cursor_position(), (2, 3));
+
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Enter));
+        state.handle_action(text_editor::Action::Edit(paste("1\n2")));
+        assert_eq!(lines(&state), ["/x1", "/x2", "/x3"]);
+
+        state.handle_action(text_editor::Action::SelectLine);
+        assert!(state.cursors.is_empty());
+
+        move_cursor(&mut state, 0);
+        state.handle_cursor_action(CursorAction::Extend(text_editor::Motion::Down));
+        state.handle_cursor_action(CursorAction::Extend(text_editor::Motion::Right));
+        state.handle_cursor_action(CursorAction::Extend(text_editor::Motion::Right));
+        state.handle_cursor_action(CursorAction::Add(2, 10));
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Delete));
+        assert_eq!(lines(&state), ["/1", "/2", "/x3"]);
+    }
+
+    #[test]
+    fn test_original_lines_and_show_only_changed() {
+        let mut state = editor_state(&["/dir/a", "/dir/b", "/c"]);
+        let mut options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/dir/renamed");
+        assert_eq!(state.original_lines, ["/dir/a", "/dir/b", "/c"]);
+        assert_eq!(state.longest_original_line, 6);
+
+        options.show_only_changed = true;
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/dir/renamed"]);
+        assert_eq!(state.original_lines, ["/dir/b"]);
+
+        replace_line(&mut state, "/dir/b");
+        assert_eq!(lines(&state), ["/dir/b"]);
+
+        options.display_type.selected = Some(DisplayType::JustName);
+        state.show_filtered_entries(&options, &filters);
+        assert!(state.original_lines.is_empty());
+
+        options.show_only_changed = false;
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(state.original_lines, ["a", "b", "c"]);
+        assert_eq!(state.longest_original_line, 1);
+    }
+
+    #[test]
+    fn test_line_info_flags_duplicate_targets() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        let duplicates = |state: &EditorState| {
+            state
+                .line_info
+                .iter()
+                .map(|info| info.is_duplicate)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(duplicates(&state), [false, false, false]);
+
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/c");
+        assert_eq!(duplicates(&state), [false, true, true]);
+
+        move_cursor(&mut state, 2);
+        replace_line(&mut state, "/b");
+        assert_eq!(duplicates(&state), [false, false, false]);
+
+        state.undo();
+        assert_eq!(duplicates(&state), [false, true, true]);
+    }
+
+    fn find(state: &mut EditorState, input: &str, use_regex: bool) {
+        state.find_and_replace.find.input = input.to_owned();
+        state.find_and_replace.find.use_regex = use_regex;
+        state.find_and_replace.find.update_regex();
+        state.update_occurences();
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        state.contents = content_with_cursor(&state.contents.text(), 0, 2);
+        for c in ['x', 'y'] {
+            state.handle_action(text_editor::Action::Edit(text_editor::Edit::Insert(c)));
+        }
+        state.handle_action(text_editor::Action::Edit(text_editor::Edit::Backspace));
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/d");
+        assert_eq!(lines(&state), ["/ax", "/d", "/c"]);
+
+        filters.filter_input.state.use_regex = true;
+        filter(&mut filters, &state, "b|c");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/d", "/c"]);
+
+        state.undo();
+        assert_eq!(lines(&state), ["/b", "/c"]);
+        state.undo();
+        assert_eq!(state.entries[0].path, "/a");
+        assert_eq!(state.pending_edit_count(), (0, 0));
+        assert!(!state.can_undo());
+
+        state.redo();
+        assert_eq!(state.entries[0].path, "/ax");
+        state.reset_all_entries();
+        state.undo();
+        assert_eq!(state.entries[0].path, "/ax");
+        state.redo();
+        assert_eq!(state.entries[0].path, "/a");
+        assert!(!state.can_redo());
+    }
+
+    #[test]
+    fn test_typing_after_undo_starts_a_new_step() {
+        let mut state = editor_state(&["/a", "/b"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        let type_at = |state: &mut EditorState, row: usize, c: char| {
+            let column = lines(state)[row].len();
+            state.contents = content_with_cursor(&state.contents.text(), row, column);
+            state.handle_action(text_editor::Action::Edit(text_editor::Edit::Insert(c)));
+        };
+        type_at(&mut state, 0, 'x');
+        type_at(&mut state, 1, 'y');
+        state.undo();
+        type_at(&mut state, 0, 'z');
+        assert_eq!(lines(&state), ["/axz", "/b"]);
+
+        state.undo();
+        assert_eq!(lines(&state), ["/ax", "/b"]);
+        state.undo();
+        assert_eq!(lines(&state), ["/a", "/b"]);
+
+        state.redo();
+        type_at(&mut state, 0, 'w');
+        state.undo();
+        assert_eq!(lines(&state), ["/ax", "/b"]);
+    }
+
+    #[test]
+    fn test_find_and_replace() {
+        let mut state = editor_state(&["/img_1.jpg", "/img_2.png", "/doc.txt"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        find(&mut state, "IMG", false);
+        assert_eq!(state.find_and_replace.occurences.len(), 2);
+        state.find_and_replace.replace = "$0".to_owned();
+        state.find_next(true);
+        state.find_next(true);
+        assert_eq!(state.contents.cursor_position(), (1, 1));
+        state.replace_current();
+        assert_eq!(lines(&state), ["/img_1.jpg", "/$0_2.png", "/doc.txt"]);
+        assert_eq!(state.contents.cursor_position(), (0, 1));
+
+        find(&mut state, r"(\w+)\.(jpg|txt)", true);
+        state.find_and_replace.replace = "$2.$1".to_owned();
+        state.replace_all();
+        assert_eq!(lines(&state), ["/jpg.img_1", "/$0_2.png", "/txt.doc"]);
+        assert!(state.find_and_replace.occurences.is_empty());
+
+        state.undo();
+        assert_eq!(lines(&state), ["/img_1.jpg", "/$0_2.png", "/doc.txt"]);
+        state.undo();
+        assert_eq!(state.pending_edit_count(), (0, 0));
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+
+        let edits = BTreeMap::from([
+            ("/a".to_owned(), "/a2".to_owned()),
+            ("/c".to_owned(), "/c2".to_owned()),
+            ("/gone".to_owned(), "/still_gone".to_owned()),
+        ]);
+        let unmatched = state.apply_edits(&edits);
+        assert_eq!(unmatched, [("/gone".to_owned(), "/still_gone".to_owned())]);
+        assert_eq!(state.pending_edit_count(), (2, 0));
+        assert_eq!(state.entries[2].path, "/c2");
+
+        state.undo();
+        assert_eq!(state.pending_edit_count(), (0, 0));
+    }
+
+    #[test]
+    fn test_show_exactly() {
+        let mut state = editor_state(&["/a.jpg", "/a.jpg.bak", "/b (1).jpg", "/sub/a.jpg"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filters.show_exactly(vec!["/a.jpg".to_owned(), "/b (1).jpg".to_owned()]);
+        filter(&mut filters, &state, "b");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/b (1).jpg"]);
+        // The drop doesn't change the text filter, so it isn't saved along with it.
+        assert_eq!(FiltersConfig::from(&filters).input, "b");
+
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/a.jpg", "/b (1).jpg"]);
+
+        filters.show_all_dropped();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state).len(), 4);
+    }
+
+    #[test]
+    fn test_refresh_keeps_edits_of_remaining_entries() {
+        let mut state = editor_state(&["/a", "/b", "/c"]);
+        let options = Options::default();
+        let mut filters = FilterOptions::default();
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        replace_line(&mut state, "/a2");
+        move_cursor(&mut state, 1);
+        replace_line(&mut state, "/b2");
+        move_cursor(&mut state, 2);
+        replace_line(&mut state, "/c2");
+
+        let removed = state.refresh(vec![entry("/new"), entry("/c"), entry("/a")]);
+        assert_eq!(removed, [("/b".to_owned(), "/b2".to_owned())]);
+        assert_eq!(lines(&state), ["/a2", "/c2"]);
+        assert_eq!(state.contents.cursor_position().0, 1);
+        assert_eq!(state.pending_edit_count(), (2, 2));
+
+        filters.resize_filters(state.entries.len());
+        filter(&mut filters, &state, "");
+        state.show_filtered_entries(&options, &filters);
+        assert_eq!(lines(&state), ["/new", "/c2", "/a2"]);
+        assert_eq!(state.pending_edit_count(), (2, 0));
+
+        state.undo();
+        assert_eq!(lines(&state), ["/new", "/c", "/a2"]);
+        state.undo();
+        state.undo();
+        assert_eq!(lines(&state), ["/new", "/c", "/a"]);
+    }
+}
@@ -1,11 +1,33 @@
+use std::{collections::HashSet, sync::Arc};
+
 use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     bit_set::BitSet,
     fs::{EntryType, FileSystemEntry},
 };
 
-use super::EditorState;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    Text,
+    MinDepth,
+    MaxDepth,
+    ShowFiles,
+    ShowFolders,
+    Dropped,
+}
+
+impl FilterKind {
+    pub const ALL: [FilterKind; 6] = [
+        FilterKind::Text,
+        FilterKind::MinDepth,
+        FilterKind::MaxDepth,
+        FilterKind::ShowFiles,
+        FilterKind::ShowFolders,
+        FilterKind::Dropped,
+    ];
+}
 
 #[derive(Debug, Clone)]
 pub struct FilterOptions {
@@ -14,6 +36,12 @@ pub struct FilterOptions {
     pub max_depth: FilterState<DepthLimit>,
     pub show_files: FilterState<bool>,
     pub show_folders: FilterState<bool>,
+    /// The paths of the entries that were dropped onto the window, which are the only ones
+    /// shown until the drop is cleared. Unlike the other filters it isn't saved.
+    pub dropped: FilterState<Option<HashSet<String>>>,
+
+    generation: u64,
+    stale: Vec<FilterKind>,
 }
 
 impl FilterOptions {
@@ -24,20 +52,101 @@ impl FilterOptions {
             &self.max_depth.is_visible,
             &self.show_files.is_visible,
             &self.show_folders.is_visible,
+            &self.dropped.is_visible,
         ]
     }
 
+    /// Resizes the visibility vectors for a new set of entries. Every entry is hidden until the
+    /// filters have been updated for the new entries.
     pub fn resize_filters(&mut self, new_size: usize) {
-        self.filter_input.is_visible.resize(new_size);
-        self.min_depth.is_visible.resize(new_size);
-        self.max_depth.is_visible.resize(new_size);
-        self.show_files.is_visible.resize(new_size);
-        self.show_folders.is_visible.resize(new_size);
+        for is_visible in [
+            &mut self.filter_input.is_visible,
+            &mut self.min_depth.is_visible,
+            &mut self.max_depth.is_visible,
+            &mut self.show_files.is_visible,
+            &mut self.show_folders.is_visible,
+            &mut self.dropped.is_visible,
+        ] {
+            is_visible.clear_all();
+            is_visible.resize(new_size);
+        }
+    }
+
+    /// Shows only the entries with the given paths, on top of the other filters.
+    pub fn show_exactly(&mut self, paths: Vec<String>) {
+        self.dropped.state = Some(paths.into_iter().collect());
+    }
+
+    /// Shows the entries that [`FilterOptions::show_exactly`] hid again.
+    pub fn show_all_dropped(&mut self) {
+        self.dropped.state = None;
     }
 
-    pub fn update_text_filter(&mut self, editor_state: &EditorState) {
-        self.filter_input.state.update_regex();
-        self.apply_text_filter(editor_state);
+    /// Marks the given filters as out of date and creates a job that brings every filter that
+    /// is out of date in line with its state again.
+    ///
+    /// Starting a new job makes the results of all previously started jobs stale, so only the
+    /// most recent job's result will be accepted by [`FilterOptions::apply_job_result`].
+    pub fn start_job(
+        &mut self,
+        kinds: &[FilterKind],
+        entries: &Arc<Vec<FileSystemEntry>>,
+    ) -> FilterJob {
+        for kind in kinds {
+            if !self.stale.contains(kind) {
+                self.stale.push(*kind);
+            }
+        }
+        if kinds.contains(&FilterKind::Text) {
+            self.filter_input.state.update_regex();
+        }
+        self.generation += 1;
+        FilterJob {
+            generation: self.generation,
+            kinds: self.stale.clone(),
+            filters: self.clone(),
+            entries: Arc::clone(entries),
+        }
+    }
+
+    /// Takes over the visibility vectors computed by a job. Returns whether the result was
+    /// accepted, which only happens if no other job has been started since.
+    pub fn apply_job_result(&mut self, result: FilterJobResult) -> bool {
+        if result.generation != self.generation {
+            return false;
+        }
+        let mut filters = *result.filters;
+        for kind in result.kinds {
+            let (target, source) = match kind {
+                FilterKind::Text => (
+                    &mut self.filter_input.is_visible,
+                    &mut filters.filter_input.is_visible,
+                ),
+                FilterKind::MinDepth => (
+                    &mut self.min_depth.is_visible,
+                    &mut filters.min_depth.is_visible,
+                ),
+                FilterKind::MaxDepth => (
+                    &mut self.max_depth.is_visible,
+                    &mut filters.max_depth.is_visible,
+                ),
+                FilterKind::ShowFiles => (
+                    &mut self.show_files.is_visible,
+                    &mut filters.show_files.is_visible,
+                ),
+                FilterKind::ShowFolders => (
+                    &mut self.show_folders.is_visible,
+                    &mut filters.show_folders.is_visible,
+                ),
+                FilterKind::Dropped => (
+                    &mut self.dropped.is_visible,
+                    &mut filters.dropped.is_visible,
+                ),
+            };
+            *target = std::mem::take(source);
+        }
+        self.stale.clear();
+        true
     }
 
     pub fn normalize_min_depth(&mut self) {
@@ -58,66 +167,106 @@ impl FilterOptions {
         }
     }
 
-    pub fn update_min_depth(&mut self, editor_state: &EditorState) {
+    fn update(&mut self, kind: FilterKind, entries: &[FileSystemEntry]) {
+        match kind {
+            FilterKind::Text => self.apply_text_filter(entries),
+            FilterKind::MinDepth => self.update_min_depth(entries),
+            FilterKind::MaxDepth => self.update_max_depth(entries),
+            FilterKind::ShowFiles => self.update_show_files(entries),
+            FilterKind::ShowFolders => self.update_show_folders(entries),
+            FilterKind::Dropped => self.update_dropped(entries),
+        }
+    }
+
+    fn update_min_depth(&mut self, entries: &[FileSystemEntry]) {
         let is_active = self.min_depth.state.is_active;
         let limit = self.min_depth.state.limit.unwrap_or(0);
         self.min_depth
-            .update(editor_state, |entry| !is_active || entry.depth >= limit);
+            .update(entries, |entry| !is_active || entry.depth >= limit);
     }
 
-    pub fn update_max_depth(&mut self, editor_state: &EditorState) {
+    fn update_max_depth(&mut self, entries: &[FileSystemEntry]) {
         let is_active = self.max_depth.state.is_active;
         let limit = self.max_depth.state.limit.unwrap_or(usize::MAX);
         self.max_depth
-            .update(editor_state, |entry| !is_active || entry.depth <= limit);
+            .update(entries, |entry| !is_active || entry.depth <= limit);
     }
 
-    pub fn update_show_files(&mut self, editor_state: &EditorState) {
+    fn update_show_files(&mut self, entries: &[FileSystemEntry]) {
         let should_show_files = self.show_files.state;
-        self.show_files.update(editor_state, |entry| {
+        self.show_files.update(entries, |entry| {
             should_show_files || !matches!(entry.entry_type, EntryType::File)
         });
     }
 
-    pub fn update_show_folders(&mut self, editor_state: &EditorState) {
+    fn update_show_folders(&mut self, entries: &[FileSystemEntry]) {
         let should_show_folders = self.show_folders.state;
-        self.show_folders.update(editor_state, |entry| {
+        self.show_folders.update(entries, |entry| {
             should_show_folders || !matches!(entry.entry_type, EntryType::Folder)
         });
     }
 
-    fn apply_text_filter(&mut self, editor_state: &EditorState) {
+    fn update_dropped(&mut self, entries: &[FileSystemEntry]) {
+        let dropped = &self.dropped.state;
+        self.dropped.is_visible.par_set_from(entries, |entry| {
+            dropped
+                .as_ref()
+                .is_none_or(|paths| paths.contains(&entry.og_path))
+        });
+    }
+
+    fn apply_text_filter(&mut self, entries: &[FileSystemEntry]) {
         let re = match &self.filter_input.state.regex {
             Some(Ok(re)) => Some(re),
             Some(Err((re, _))) => re.as_ref(),
             _ => None,
         };
+        let case_sensitive = self.filter_input.state.case_sensitive;
+        let input = if case_sensitive {
+            self.filter_input.state.input.clone()
+        } else {
+            self.filter_input.state.input.to_lowercase()
+        };
 
         self.filter_input
             .is_visible
-            .resize(editor_state.entries.len());
-        (0..self.filter_input.is_visible.size)
-            .zip(editor_state.entries.iter())
-            .for_each(|(i, entry)| {
-                let is_set = match &re {
-                    Some(re) if re.is_match(&entry.path) => true,
-                    None => {
-                        if self.filter_input.state.case_sensitive {
-                            entry.path.contains(&self.filter_input.state.input)
-                        } else {
-                            entry
-                                .path
-                                .to_lowercase()
-                                .contains(&self.filter_input.state.input.to_lowercase())
-                        }
-                    }
-                    _ => false,
-                };
-                self.filter_input.is_visible.set_bit(i, is_set);
+            .par_set_from(entries, |entry| match &re {
+                Some(re) => re.is_match(&entry.og_path),
+                None if case_sensitive => entry.og_path.contains(&input),
+                None => entry.og_path.to_lowercase().contains(&input),
             });
     }
 }
 
+/// A snapshot of the filters and entries that can be evaluated away from the UI thread.
+#[derive(Debug)]
+pub struct FilterJob {
+    generation: u64,
+    kinds: Vec<FilterKind>,
+    filters: FilterOptions,
+    entries: Arc<Vec<FileSystemEntry>>,
+}
+
+impl FilterJob {
+    pub fn run(mut self) -> FilterJobResult {
+        for kind in self.kinds.iter() {
+            self.filters.update(*kind, &self.entries);
+        }
+        FilterJobResult {
+            generation: self.generation,
+            kinds: self.kinds,
+            filters: Box::new(self.filters),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterJobResult {
+    generation: u64,
+    kinds: Vec<FilterKind>,
+    filters: Box<FilterOptions>,
+}
+
 impl Default for FilterOptions {
     fn default() -> Self {
         Self {
@@ -126,6 +275,9 @@ impl Default for FilterOptions {
             max_depth: FilterState::new(Default::default()),
             show_files: FilterState::new(true),
             show_folders: FilterState::new(true),
+            dropped: FilterState::new(None),
+            generation: 0,
+            stale: Vec::new(),
         }
     }
 }
@@ -144,16 +296,11 @@ impl<T> FilterState<T> {
         }
     }
 
-    pub fn update<F>(&mut self, editor_state: &EditorState, op: F)
+    pub fn update<F>(&mut self, entries: &[FileSystemEntry], op: F)
     where
-        F: Fn(&FileSystemEntry) -> bool,
+        F: Fn(&FileSystemEntry) -> bool + Sync,
     {
-        self.is_visible.resize(editor_state.entries.len());
-        (0..self.is_visible.size)
-            .zip(editor_state.entries.iter())
-            .for_each(|(i, entry)| {
-                self.is_visible.set_bit(i, op(entry));
-            });
+        self.is_visible.par_set_from(entries, op);
     }
 }
 
@@ -196,7 +343,8 @@ impl FilterInput {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DepthLimit {
     pub is_active: bool,
     pub limit: Option<usize>,
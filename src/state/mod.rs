@@ -1,7 +1,9 @@
 mod editor;
 mod filters;
 mod options;
+mod sorting;
 
 pub use editor::*;
 pub use filters::*;
 pub use options::*;
+pub use sorting::*;
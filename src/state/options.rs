@@ -5,9 +5,17 @@ use iced::Theme;
 #[derive(Debug, Clone)]
 pub struct Options {
     pub sorting: DropDownState<SortingOption>,
+    pub secondary_sorting: DropDownState<SortingOption>,
+    pub directories_first: bool,
     pub display_type: DropDownState<DisplayType>,
+    pub show_only_changed: bool,
     pub remove_empty: bool,
     pub preview_changes: bool,
+    pub allow_outside_root: bool,
+    /// Whether the opened folders are rescanned whenever something changes in them.
+    pub watch_folders: bool,
+    /// Whether entries tracked by git are renamed with `git mv`.
+    pub use_git: bool,
     pub theme: DropDownState<Theme>,
 }
 
@@ -18,12 +26,21 @@ impl Default for Options {
                 selected: Some(SortingOption::NoSorting),
                 options: SortingOption::variants(),
             },
+            secondary_sorting: DropDownState {
+                selected: Some(SortingOption::NoSorting),
+                options: SortingOption::variants(),
+            },
+            directories_first: false,
             display_type: DropDownState {
                 selected: Some(DisplayType::RelativePath),
                 options: DisplayType::variants(),
             },
+            show_only_changed: false,
             remove_empty: false,
             preview_changes: true,
+            allow_outside_root: false,
+            watch_folders: false,
+            use_git: false,
             theme: DropDownState {
                 selected: Some(Theme::Light),
                 options: Theme::ALL.to_vec(),
@@ -45,7 +62,8 @@ macro_rules! drop_down_enum {
         ,
         *
     ) => {
-        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "snake_case")]
         pub enum $name {
             $(
                 $variant,
@@ -78,6 +96,16 @@ drop_down_enum!(
     SortAscendingCaseInsensitive,
     SortAscendingCaseSensitive,
     SortDescendingCaseInsensitive,
-    SortDescendingCaseSensitive
+    SortDescendingCaseSensitive,
+    SortNaturalAscending,
+    SortNaturalDescending,
+    SortByExtensionAscending,
+    SortByExtensionDescending,
+    SortBySizeAscending,
+    SortBySizeDescending,
+    SortByModifiedAscending,
+    SortByModifiedDescending,
+    SortByDepthAscending,
+    SortByDepthDescending
 );
 drop_down_enum!(DisplayType, AbsolutePath, RelativePath, JustName);
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+
+use crate::fs::{EntryType, FileSystemEntry};
+
+use super::{Options, SortingOption};
+
+/// The full order entries are shown in: optionally directories before files, followed by any
+/// number of sort keys. Entries that compare equal on every key keep the order they were found
+/// in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortOrder {
+    pub directories_first: bool,
+    pub keys: Vec<SortingOption>,
+}
+
+impl SortOrder {
+    pub fn from_options(options: &Options) -> Self {
+        Self {
+            directories_first: options.directories_first,
+            keys: [options.sorting.selected, options.secondary_sorting.selected]
+                .into_iter()
+                .flatten()
+                .filter(|option| *option != SortingOption::NoSorting)
+                .collect(),
+        }
+    }
+
+    pub fn is_unsorted(&self) -> bool {
+        !self.directories_first && self.keys.is_empty()
+    }
+
+    pub fn compare(&self, a: &SortItem, b: &SortItem) -> Ordering {
+        let directories = if self.directories_first {
+            is_file(a.entry).cmp(&is_file(b.entry))
+        } else {
+            Ordering::Equal
+        };
+        self.keys.iter().fold(directories, |ordering, key| {
+            ordering.then_with(|| compare_by(*key, a, b))
+        })
+    }
+}
+
+/// An entry together with the text its original path is displayed as, which the name based sort
+/// keys use.
+pub struct SortItem<'a> {
+    pub entry: &'a FileSystemEntry,
+    pub display: &'a str,
+}
+
+impl<'a> SortItem<'a> {
+    fn extension(&self) -> &str {
+        match self.entry.entry_type {
+            EntryType::File => {
+                let name = self.entry.og_name();
+                match name.rfind('.') {
+                    Some(i) if i > 0 => &name[i + 1..],
+                    _ => "",
+                }
+            }
+            EntryType::Folder => "",
+        }
+    }
+}
+
+fn is_file(entry: &FileSystemEntry) -> bool {
+    matches!(entry.entry_type, EntryType::File)
+}
+
+fn compare_by(key: SortingOption, a: &SortItem, b: &SortItem) -> Ordering {
+    match key {
+        SortingOption::NoSorting => Ordering::Equal,
+        SortingOption::SortAscendingCaseInsensitive => case_insensitive_cmp(a.display, b.display),
+        SortingOption::SortAscendingCaseSensitive => a.display.cmp(b.display),
+        SortingOption::SortDescendingCaseInsensitive => {
+            case_insensitive_cmp(a.display, b.display).reverse()
+        }
+        SortingOption::SortDescendingCaseSensitive => a.display.cmp(b.display).reverse(),
+        SortingOption::SortNaturalAscending => natural_cmp(a.display, b.display),
+        SortingOption::SortNaturalDescending => natural_cmp(a.display, b.display).reverse(),
+        SortingOption::SortByExtensionAscending => {
+            case_insensitive_cmp(a.extension(), b.extension())
+        }
+        SortingOption::SortByExtensionDescending => {
+            case_insensitive_cmp(a.extension(), b.extension()).reverse()
+        }
+        SortingOption::SortBySizeAscending => a.entry.size.cmp(&b.entry.size),
+        SortingOption::SortBySizeDescending => a.entry.size.cmp(&b.entry.size).reverse(),
+        SortingOption::SortByModifiedAscending => a.entry.modified.cmp(&b.entry.modified),
+        SortingOption::SortByModifiedDescending => {
+            a.entry.modified.cmp(&b.entry.modified).reverse()
+        }
+        SortingOption::SortByDepthAscending => a.entry.depth.cmp(&b.entry.depth),
+        SortingOption::SortByDepthDescending => a.entry.depth.cmp(&b.entry.depth).reverse(),
+    }
+}
+
+fn case_insensitive_cmp(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Compares two strings the way a human would, treating runs of digits as numbers so that
+/// `file2` comes before `file10`. Text is compared case insensitively, and strings that are
+/// only different in case or leading zeros are ordered by a plain comparison to keep the
+/// order total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = Chunks(a);
+    let mut b_chunks = Chunks(b);
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a_chunk), Some(b_chunk)) => compare_chunks(a_chunk, b_chunk),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn compare_chunks(a: &str, b: &str) -> Ordering {
+    match (is_number(a), is_number(b)) {
+        (true, true) => {
+            let a = a.trim_start_matches('0');
+            let b = b.trim_start_matches('0');
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        }
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => case_insensitive_cmp(a, b),
+    }
+}
+
+fn is_number(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Splits a string into alternating runs of ASCII digits and everything else.
+struct Chunks<'a>(&'a str);
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.0.chars().next()?;
+        let is_digit = first.is_ascii_digit();
+        let end = self
+            .0
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(self.0.len());
+        let (chunk, rest) = self.0.split_at(end);
+        self.0 = rest;
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("file2", "file10", Ordering::Less)]
+    #[case("file10", "file2", Ordering::Greater)]
+    #[case("file10", "file10", Ordering::Equal)]
+    #[case("File2", "file10", Ordering::Less)]
+    #[case("a", "B", Ordering::Less)]
+    #[case("img_009.jpg", "img_10.jpg", Ordering::Less)]
+    #[case("img_01.jpg", "img_1.jpg", Ordering::Less)]
+    #[case("x", "x1", Ordering::Less)]
+    #[case("1x", "x", Ordering::Less)]
+    #[case("track 2 - b", "track 2 - a", Ordering::Greater)]
+    #[case("/a/10/x", "/a/9/y", Ordering::Greater)]
+    fn test_natural_cmp(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        assert_eq!(natural_cmp(a, b), expected);
+        assert_eq!(natural_cmp(b, a), expected.reverse());
+    }
+
+    #[test]
+    fn test_natural_sort() {
+        let mut names = vec!["file10", "file2", "file1", "File3", "file02"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["file1", "file02", "file2", "File3", "file10"]);
+    }
+}
@@ -1,10 +1,612 @@
-use iced::{widget::text_editor, Element, Length};
+use iced::{
+    advanced::{
+        layout, renderer,
+        text::{self, Paragraph},
+        widget::{tree, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    alignment, event, keyboard, mouse,
+    widget::{responsive, scrollable, text::LineHeight, text_editor},
+    Background, Color, Element, Event, Font, Length, Point, Rectangle, Renderer, Size, Theme,
+};
 
-use crate::{mareto::Message, state::EditorState};
+use std::{ops::Range, sync::Arc};
 
-pub fn editor(state: &EditorState) -> Element<'_, Message> {
-    text_editor(&state.contents)
-        .on_action(Message::EditAction)
-        .height(Length::Fill)
-        .into()
+use crate::{
+    diff::changed_chars,
+    fs::{is_invalid_name_char, FileSystemEntry},
+    mareto::Message,
+    shortcuts::Shortcuts,
+    state::{CursorAction, EditorState, LineCursor},
+};
+
+use super::highlighter::{PathHighlight, PathHighlighter};
+
+const PADDING: f32 = 5.0;
+const MARKER_WIDTH: f32 = 4.0;
+
+pub fn editor<'a>(state: &'a EditorState, shortcuts: &'a Shortcuts) -> Element<'a, Message> {
+    responsive(move |size| {
+        scrollable(CursorEditor::new(state, shortcuts, size))
+            .id(editor_scrollable())
+            .direction(scrollable::Direction::Both {
+                vertical: scrollable::Properties::default(),
+                horizontal: scrollable::Properties::default(),
+            })
+            .into()
+    })
+    .into()
+}
+
+pub fn editor_scrollable() -> scrollable::Id {
+    scrollable::Id::new("editor")
+}
+
+/// A text editor that can also edit at several cursors and select columns.
+///
+/// The text editor is laid out at full height and wide enough for the longest line inside of a
+/// scrollable, and uses a monospace font. That way every row and column sits at a fixed position,
+/// so the extra cursors can be drawn and mouse positions turned into rows and columns without
+/// any help from the text editor.
+///
+/// Alt+click adds a cursor, Alt+drag selects a column and Alt+Shift+arrows extend the selection.
+/// Key presses that are bound to a shortcut, like undo and redo, are left to the application, as
+/// the text editor has no history of its own. Ctrl+Y redoes as well.
+///
+/// A read-only column to the left of the text editor shows the original name of every line. Lines
+/// with a pending change are marked, and the characters that differ are highlighted on both sides.
+struct CursorEditor<'a> {
+    editor: Element<'a, Message>,
+    contents: &'a text_editor::Content,
+    cursors: &'a [LineCursor],
+    longest_line: usize,
+    entries: &'a [FileSystemEntry],
+    filtered_indices: &'a [usize],
+    original_lines: &'a [String],
+    longest_original_line: usize,
+    shortcuts: &'a Shortcuts,
+    min_size: Size,
+}
+
+impl<'a> CursorEditor<'a> {
+    fn new(state: &'a EditorState, shortcuts: &'a Shortcuts, min_size: Size) -> Self {
+        let editor = text_editor(&state.contents)
+            .highlight::<PathHighlighter>(Arc::clone(&state.line_info), PathHighlight::format)
+            .on_action(Message::EditAction)
+            .font(Font::MONOSPACE)
+            .padding(PADDING)
+            .height(Length::Shrink)
+            .into();
+        Self {
+            editor,
+            contents: &state.contents,
+            cursors: &state.cursors,
+            longest_line: state.longest_line,
+            entries: &state.entries,
+            filtered_indices: &state.filtered_indices,
+            original_lines: &state.original_lines,
+            longest_original_line: state.longest_original_line,
+            shortcuts,
+            min_size,
+        }
+    }
+
+    fn original_column_width(&self, char_width: f32) -> f32 {
+        if self.original_lines.is_empty() {
+            0.0
+        } else {
+            MARKER_WIDTH + (self.longest_original_line + 1) as f32 * char_width + 2.0 * PADDING
+        }
+    }
+
+    fn is_changed(&self, row: usize, line: &str) -> bool {
+        let is_entry_changed = self
+            .filtered_indices
+            .get(row)
+            .is_some_and(|i| self.entries[*i].is_changed());
+        is_entry_changed || self.original_lines.get(row).is_some_and(|og| og != line)
+    }
+
+    /// Draws the original lines that are in view, marking changed lines and highlighting the
+    /// characters that differ from the line in the text editor.
+    fn draw_original_lines(
+        &self,
+        state: &State,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        layout: Layout<'_>,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let editor_bounds = editor_layout(layout).bounds();
+        let column = Rectangle::new(
+            bounds.position(),
+            Size::new(editor_bounds.x - bounds.x, bounds.height),
+        );
+        let palette = theme.extended_palette();
+        let removed = Color {
+            a: 0.4,
+            ..palette.danger.weak.color
+        };
+        let added = Color {
+            a: 0.4,
+            ..palette.success.weak.color
+        };
+        renderer::Renderer::fill_quad(
+            renderer,
+            renderer::Quad {
+                bounds: column,
+                ..Default::default()
+            },
+            Background::Color(palette.background.weak.color),
+        );
+
+        let text_start = Rectangle {
+            x: bounds.x + MARKER_WIDTH,
+            ..bounds
+        };
+        for row in state.visible_rows(bounds, viewport) {
+            let Some(original) = self.original_lines.get(row) else {
+                break;
+            };
+            let line = self.contents.line(row);
+            let line = line.as_deref().unwrap_or("");
+            if self.is_changed(row, line) {
+                let marker = renderer::Quad {
+                    bounds: Rectangle::new(
+                        Point::new(bounds.x, state.position_of(bounds, row, 0).y),
+                        Size::new(MARKER_WIDTH, state.line_height),
+                    ),
+                    ..Default::default()
+                };
+                renderer::Renderer::fill_quad(
+                    renderer,
+                    marker,
+                    Background::Color(palette.primary.strong.color),
+                );
+                let (old_ranges, new_ranges) = changed_chars(original, line);
+                for (area, ranges, color) in [
+                    (text_start, old_ranges, removed),
+                    (editor_bounds, new_ranges, added),
+                ] {
+                    for range in ranges {
+                        let quad = renderer::Quad {
+                            bounds: Rectangle::new(
+                                state.position_of(area, row, range.start),
+                                Size::new(range.len() as f32 * state.char_width, state.line_height),
+                            ),
+                            ..Default::default()
+                        };
+                        renderer::Renderer::fill_quad(renderer, quad, Background::Color(color));
+                    }
+                }
+            }
+            text::Renderer::fill_text(
+                renderer,
+                text::Text {
+                    content: original,
+                    bounds: Size::new(f32::INFINITY, state.line_height),
+                    size: text::Renderer::default_size(renderer),
+                    line_height: LineHeight::default(),
+                    font: Font::MONOSPACE,
+                    horizontal_alignment: alignment::Horizontal::Left,
+                    vertical_alignment: alignment::Vertical::Top,
+                    shaping: text::Shaping::Advanced,
+                },
+                state.position_of(text_start, row, 0),
+                palette.background.weak.text,
+                column,
+            );
+        }
+    }
+
+    /// Forwards an event to the text editor. Clicks are dropped if `keep_clicks` is false, so
+    /// that the text editor still gets focused without moving its cursor.
+    #[allow(clippy::too_many_arguments)]
+    fn forward(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+        keep_clicks: bool,
+    ) -> event::Status {
+        let mut messages = Vec::new();
+        let mut editor_shell = Shell::new(&mut messages);
+        let status = self.editor.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            editor_layout(layout),
+            cursor,
+            renderer,
+            clipboard,
+            &mut editor_shell,
+            viewport,
+        );
+        let is_layout_invalid = editor_shell.is_layout_invalid();
+        let are_widgets_invalid = editor_shell.are_widgets_invalid();
+        let redraw_request = editor_shell.redraw_request();
+        if is_layout_invalid {
+            shell.invalidate_layout();
+        }
+        if are_widgets_invalid {
+            shell.invalidate_widgets();
+        }
+        if let Some(request) = redraw_request {
+            shell.request_redraw(request);
+        }
+        for message in messages {
+            let is_click = matches!(
+                message,
+                Message::EditAction(text_editor::Action::Click(_) | text_editor::Action::Drag(_))
+            );
+            if keep_clicks || !is_click {
+                shell.publish(message);
+            }
+        }
+        status
+    }
+
+    /// Scrolls the text editor's cursor into view if it moved.
+    fn follow_cursor(
+        &self,
+        state: &mut State,
+        layout: Layout<'_>,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) {
+        let position = self.contents.cursor_position();
+        if state.last_cursor == Some(position) {
+            return;
+        }
+        state.last_cursor = Some(position);
+        if !state.is_focused {
+            return;
+        }
+        let (row, col) = position;
+        let column = self
+            .contents
+            .line(row)
+            .map_or(0, |line| line[..col.min(line.len())].chars().count());
+        let bounds = layout.bounds();
+        let caret = state.position_of(editor_layout(layout).bounds(), row, column);
+        let offset_x = viewport.x - bounds.x;
+        let offset_y = viewport.y - bounds.y;
+        let target_x = scroll_into_view(
+            offset_x,
+            viewport.width,
+            caret.x - bounds.x,
+            state.char_width,
+        );
+        let target_y = scroll_into_view(
+            offset_y,
+            viewport.height,
+            caret.y - bounds.y,
+            state.line_height,
+        );
+        if target_x != offset_x || target_y != offset_y {
+            shell.publish(Message::ScrollEditorTo(scrollable::AbsoluteOffset {
+                x: target_x,
+                y: target_y,
+            }));
+        }
+    }
+}
+
+/// Returns the scroll offset that shows the range starting at `start` with the given size, keeping
+/// the current offset if it is already visible.
+fn scroll_into_view(offset: f32, visible: f32, start: f32, size: f32) -> f32 {
+    if start < offset {
+        (start - PADDING).max(0.0)
+    } else if start + size > offset + visible {
+        start + size + PADDING - visible
+    } else {
+        offset
+    }
+}
+
+fn editor_layout(layout: Layout<'_>) -> Layout<'_> {
+    layout
+        .children()
+        .next()
+        .expect("The text editor is the only child")
+}
+
+#[derive(Debug, Default)]
+struct State {
+    char_width: f32,
+    line_height: f32,
+    modifiers: keyboard::Modifiers,
+    is_focused: bool,
+    drag_start: Option<(usize, usize)>,
+    last_cursor: Option<(usize, usize)>,
+}
+
+impl State {
+    /// The row and column closest to a position relative to the editor.
+    fn row_and_column(&self, position: Point) -> (usize, usize) {
+        let row = ((position.y - PADDING) / self.line_height).floor().max(0.0);
+        let column = ((position.x - PADDING) / self.char_width).round().max(0.0);
+        (row as usize, column as usize)
+    }
+
+    /// The rows that are at least partly inside of the viewport.
+    fn visible_rows(&self, bounds: Rectangle, viewport: &Rectangle) -> Range<usize> {
+        let (first, _) = self.row_and_column(Point::new(0.0, viewport.y - bounds.y));
+        let (last, _) =
+            self.row_and_column(Point::new(0.0, viewport.y + viewport.height - bounds.y));
+        first..last + 1
+    }
+
+    /// The top left corner of the character at a row and column.
+    fn position_of(&self, bounds: Rectangle, row: usize, column: usize) -> Point {
+        Point::new(
+            bounds.x + PADDING + column as f32 * self.char_width,
+            bounds.y + PADDING + row as f32 * self.line_height,
+        )
+    }
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for CursorEditor<'a> {
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Shrink, Length::Shrink)
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.editor)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.editor));
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let state = tree.state.downcast_mut::<State>();
+        let size = text::Renderer::default_size(renderer);
+        let sample = "0".repeat(10);
+        let paragraph = <Renderer as text::Renderer>::Paragraph::with_text(text::Text {
+            content: &sample,
+            bounds: Size::INFINITY,
+            size,
+            line_height: LineHeight::default(),
+            font: Font::MONOSPACE,
+            horizontal_alignment: alignment::Horizontal::Left,
+            vertical_alignment: alignment::Vertical::Top,
+            shaping: text::Shaping::Basic,
+        });
+        state.char_width = paragraph.min_width() / 10.0;
+        state.line_height = LineHeight::default().to_absolute(size).0;
+
+        // One extra column leaves room for the cursor at the end of the longest line.
+        let column_width = self.original_column_width(state.char_width);
+        let text_width = (self.longest_line + 1) as f32 * state.char_width + 2.0 * PADDING;
+        let width = text_width.max(self.min_size.width - column_width);
+        let editor_limits = layout::Limits::new(
+            Size::new(width, self.min_size.height),
+            Size::new(width, limits.max().height),
+        );
+        let editor = self
+            .editor
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, &editor_limits)
+            .move_to(Point::new(column_width, 0.0));
+        let size = Size::new(column_width + width, editor.size().height);
+        layout::Node::with_children(size, vec![editor])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.editor.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            editor_layout(layout),
+            cursor,
+            viewport,
+        );
+
+        let state = tree.state.downcast_ref::<State>();
+        if !self.original_lines.is_empty() {
+            self.draw_original_lines(state, renderer, theme, layout, viewport);
+        }
+
+        let bounds = editor_layout(layout).bounds();
+        let palette = theme.extended_palette();
+        let selection = Color {
+            a: 0.4,
+            ..palette.primary.weak.color
+        };
+        for line_cursor in self.cursors {
+            let (start, end) = line_cursor.range();
+            if end > start {
+                let quad = renderer::Quad {
+                    bounds: Rectangle::new(
+                        state.position_of(bounds, line_cursor.row, start),
+                        Size::new((end - start) as f32 * state.char_width, state.line_height),
+                    ),
+                    ..Default::default()
+                };
+                renderer::Renderer::fill_quad(renderer, quad, Background::Color(selection));
+            }
+            let caret = renderer::Quad {
+                bounds: Rectangle::new(
+                    state.position_of(bounds, line_cursor.row, line_cursor.column),
+                    Size::new(1.0, state.line_height),
+                ),
+                ..Default::default()
+            };
+            renderer::Renderer::fill_quad(
+                renderer,
+                caret,
+                Background::Color(palette.background.base.text),
+            );
+        }
+
+        for row in state.visible_rows(bounds, viewport) {
+            let Some(line) = self.contents.line(row) else {
+                break;
+            };
+            for (column, _) in line
+                .chars()
+                .enumerate()
+                .filter(|(_, c)| is_invalid_name_char(*c))
+            {
+                let underline = renderer::Quad {
+                    bounds: Rectangle::new(
+                        state.position_of(bounds, row, column)
+                            + iced::Vector::new(0.0, state.line_height - 2.0),
+                        Size::new(state.char_width, 2.0),
+                    ),
+                    ..Default::default()
+                };
+                renderer::Renderer::fill_quad(
+                    renderer,
+                    underline,
+                    Background::Color(palette.danger.strong.color),
+                );
+            }
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let bounds = editor_layout(layout).bounds();
+        let state = tree.state.downcast_mut::<State>();
+        self.follow_cursor(state, layout, shell, viewport);
+
+        match &event {
+            // The scrollable around the editor handles scrolling.
+            Event::Mouse(mouse::Event::WheelScrolled { .. }) => return event::Status::Ignored,
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = *modifiers;
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                state.is_focused = cursor.is_over(bounds);
+                if let Some(position) = cursor.position_in(bounds) {
+                    if state.modifiers.alt() {
+                        let (row, column) = state.row_and_column(position);
+                        state.drag_start = Some((row, column));
+                        shell.publish(Message::CursorAction(CursorAction::Add(row, column)));
+                        self.forward(
+                            tree, event, layout, cursor, renderer, clipboard, shell, viewport,
+                            false,
+                        );
+                        return event::Status::Captured;
+                    }
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                if let (Some(from), Some(position)) = (state.drag_start, cursor.position_in(bounds))
+                {
+                    let to = state.row_and_column(position);
+                    shell.publish(Message::CursorAction(CursorAction::SelectColumns {
+                        from,
+                        to,
+                    }));
+                    return event::Status::Captured;
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.drag_start.is_some() =>
+            {
+                state.drag_start = None;
+                self.forward(
+                    tree, event, layout, cursor, renderer, clipboard, shell, viewport, false,
+                );
+                return event::Status::Captured;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. })
+                if self.shortcuts.action_for(key, *modifiers).is_some() =>
+            {
+                return event::Status::Ignored;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Character(c),
+                modifiers,
+                ..
+            }) if state.is_focused && modifiers.command() && c.eq_ignore_ascii_case("y") => {
+                shell.publish(Message::Redo);
+                return event::Status::Captured;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(key),
+                modifiers,
+                ..
+            }) if state.is_focused && modifiers.alt() && modifiers.shift() => {
+                let motion = match key {
+                    keyboard::key::Named::ArrowUp => Some(text_editor::Motion::Up),
+                    keyboard::key::Named::ArrowDown => Some(text_editor::Motion::Down),
+                    keyboard::key::Named::ArrowLeft => Some(text_editor::Motion::Left),
+                    keyboard::key::Named::ArrowRight => Some(text_editor::Motion::Right),
+                    _ => None,
+                };
+                if let Some(motion) = motion {
+                    shell.publish(Message::CursorAction(CursorAction::Extend(motion)));
+                    return event::Status::Captured;
+                }
+            }
+            _ => {}
+        }
+        self.forward(
+            tree, event, layout, cursor, renderer, clipboard, shell, viewport, true,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.editor.as_widget().mouse_interaction(
+            &tree.children[0],
+            editor_layout(layout),
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+}
+
+impl<'a> From<CursorEditor<'a>> for Element<'a, Message> {
+    fn from(editor: CursorEditor<'a>) -> Self {
+        Element::new(editor)
+    }
 }
@@ -1,24 +1,111 @@
 use iced::{
-    widget::{button, row, text, text_input, Space},
-    Element, Length,
+    widget::{button, column, row, text, text_input, Space},
+    Alignment, Element, Length,
 };
 
-use crate::mareto::Message;
+use crate::{mareto::Message, state::EditorState};
 
-pub fn find_and_replace(open_folder: &str) -> Element<'_, Message> {
-    let s = String::new();
-    row![
-        text(open_folder),
-        Space::with_width(Length::Fill),
-        text_input("Find", &s).width(150).padding(8),
-        button("Aa").padding(8), // Case sentivite
-        button(".*").padding(8), // Use regex
-        Space::with_width(8),
-        text_input("Replace", &s).width(150).padding(8),
-        button("a").padding(8), // Next occurence
-        button("c").padding(8), // Previous occurence
-        button("d").padding(8), // Replace current occurence
-        button("r").padding(8), // Replace all
+use super::{components::toggle_button, themes::ErrorTextColor};
+
+pub fn find_and_replace(state: &EditorState) -> Element<'_, Message> {
+    let open_folder = state.roots.folders.join(", ");
+    let visible_count = format!(
+        "{} of {} shown",
+        state.is_visible.count_ones(),
+        state.entries.len()
+    );
+    let pending_edits = match state.pending_edit_count() {
+        (0, _) => String::new(),
+        (count, 0) => format!("{count} pending edits"),
+        (count, hidden) => format!("{count} pending edits ({hidden} filtered out)"),
+    };
+    let find_and_replace = &state.find_and_replace;
+    let occurences = match find_and_replace.occurences.len() {
+        0 if find_and_replace.find.input.is_empty() => String::new(),
+        0 => "No results".to_owned(),
+        count => format!("{} of {count}", find_and_replace.current_occurence + 1),
+    };
+    let regex_error_text = match &find_and_replace.find.regex {
+        Some(Err((_, text))) => text,
+        _ => "",
+    };
+    let has_occurences = !find_and_replace.occurences.is_empty();
+
+    column![
+        row![
+            text(open_folder),
+            Space::with_width(12),
+            text(visible_count),
+            Space::with_width(12),
+            text(pending_edits),
+            Space::with_width(Length::Fill),
+            button("Undo")
+                .padding(8)
+                .on_press_maybe(state.can_undo().then_some(Message::Undo)),
+            button("Redo")
+                .padding(8)
+                .on_press_maybe(state.can_redo().then_some(Message::Redo)),
+            button("Reset line")
+                .padding(8)
+                .on_press(Message::ResetCurrentEntry),
+            button("Reset all")
+                .padding(8)
+                .on_press(Message::ResetAllEntries),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center),
+        row![
+            text_input("Find", &find_and_replace.find.input)
+                .id(find_input())
+                .on_input(Message::FindUpdated)
+                .on_submit(Message::FindNext)
+                .width(200)
+                .padding(8),
+            toggle_button(
+                "Aa",
+                find_and_replace.find.case_sensitive,
+                Message::FindCaseSensitivityToggled,
+            )
+            .padding(8),
+            toggle_button(
+                ".*",
+                find_and_replace.find.use_regex,
+                Message::FindRegexToggled,
+            )
+            .padding(8),
+            text(occurences),
+            text(regex_error_text).style(ErrorTextColor),
+            Space::with_width(Length::Fill),
+            text_input("Replace", &find_and_replace.replace)
+                .id(replace_input())
+                .on_input(Message::ReplaceUpdated)
+                .on_submit(Message::ReplaceCurrent)
+                .width(200)
+                .padding(8),
+            button("Previous")
+                .padding(8)
+                .on_press_maybe(has_occurences.then_some(Message::FindPrevious)),
+            button("Next")
+                .padding(8)
+                .on_press_maybe(has_occurences.then_some(Message::FindNext)),
+            button("Replace")
+                .padding(8)
+                .on_press_maybe(has_occurences.then_some(Message::ReplaceCurrent)),
+            button("Replace all")
+                .padding(8)
+                .on_press_maybe(has_occurences.then_some(Message::ReplaceAll)),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center),
     ]
+    .spacing(8)
     .into()
 }
+
+pub fn find_input() -> text_input::Id {
+    text_input::Id::new("find")
+}
+
+pub fn replace_input() -> text_input::Id {
+    text_input::Id::new("replace")
+}
@@ -0,0 +1,134 @@
+use std::{ops::Range, sync::Arc};
+
+use iced::{advanced::text::highlighter, Color, Font, Theme};
+
+use crate::{fs::is_invalid_name_char, state::LineInfo};
+
+/// Highlights the parts of the paths in the editor: the folder is dimmed, the extension of files
+/// is coloured, and duplicate targets and invalid characters are shown in the error colour.
+pub struct PathHighlighter {
+    line_info: Arc<Vec<LineInfo>>,
+    current_line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathHighlight {
+    Folder,
+    Extension,
+    Duplicate,
+    Invalid,
+}
+
+impl PathHighlight {
+    pub fn format(&self, theme: &Theme) -> highlighter::Format<Font> {
+        let palette = theme.extended_palette();
+        let color = match self {
+            PathHighlight::Folder => Color {
+                a: 0.5,
+                ..palette.background.base.text
+            },
+            PathHighlight::Extension => palette.primary.strong.color,
+            PathHighlight::Duplicate => palette.danger.base.color,
+            PathHighlight::Invalid => palette.danger.strong.color,
+        };
+        highlighter::Format {
+            color: Some(color),
+            font: None,
+        }
+    }
+}
+
+impl highlighter::Highlighter for PathHighlighter {
+    type Settings = Arc<Vec<LineInfo>>;
+    type Highlight = PathHighlight;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, PathHighlight)>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            line_info: Arc::clone(settings),
+            current_line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.line_info = Arc::clone(new_settings);
+        self.current_line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let info = self.line_info.get(self.current_line).copied();
+        self.current_line += 1;
+        match info {
+            Some(info) => highlight_path(line, info).into_iter(),
+            None => Vec::new().into_iter(),
+        }
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+/// Splits a line into highlighted byte ranges. Later ranges take precedence over earlier ones.
+fn highlight_path(line: &str, info: LineInfo) -> Vec<(Range<usize>, PathHighlight)> {
+    let mut highlights = Vec::new();
+    let name_start = line.rfind('/').map_or(0, |i| i + 1);
+    if name_start > 0 {
+        highlights.push((0..name_start, PathHighlight::Folder));
+    }
+    let name = &line[name_start..];
+    if info.is_duplicate {
+        highlights.push((name_start..line.len(), PathHighlight::Duplicate));
+    } else if !info.is_folder {
+        // A leading dot marks a hidden file rather than an extension.
+        if let Some(dot) = name.rfind('.').filter(|dot| *dot > 0) {
+            highlights.push((name_start + dot..line.len(), PathHighlight::Extension));
+        }
+    }
+    for (i, c) in line
+        .char_indices()
+        .filter(|(_, c)| is_invalid_name_char(*c))
+    {
+        highlights.push((i..i + c.len_utf8(), PathHighlight::Invalid));
+    }
+    highlights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    const FILE: LineInfo = LineInfo {
+        is_folder: false,
+        is_duplicate: false,
+    };
+    const FOLDER: LineInfo = LineInfo {
+        is_folder: true,
+        is_duplicate: false,
+    };
+    const DUPLICATE: LineInfo = LineInfo {
+        is_folder: false,
+        is_duplicate: true,
+    };
+
+    #[rstest]
+    #[case("/a/b.txt", FILE, vec![(0..3, PathHighlight::Folder), (4..8, PathHighlight::Extension)])]
+    #[case("b.tar.gz", FILE, vec![(5..8, PathHighlight::Extension)])]
+    #[case("/.hidden", FILE, vec![(0..1, PathHighlight::Folder)])]
+    #[case("/v1.2", FOLDER, vec![(0..1, PathHighlight::Folder)])]
+    #[case("/a/b.txt", DUPLICATE, vec![(0..3, PathHighlight::Folder), (3..8, PathHighlight::Duplicate)])]
+    #[case("/ä\tb", FILE, vec![(0..1, PathHighlight::Folder), (3..4, PathHighlight::Invalid)])]
+    fn test_highlight_path(
+        #[case] line: &str,
+        #[case] info: LineInfo,
+        #[case] expected: Vec<(Range<usize>, PathHighlight)>,
+    ) {
+        assert_eq!(highlight_path(line, info), expected);
+    }
+}
@@ -1,6 +1,7 @@
 mod components;
 mod editor;
 mod find_and_replace;
+mod highlighter;
 mod options;
 mod themes;
 mod top_level_actions;
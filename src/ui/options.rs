@@ -1,12 +1,15 @@
 use crate::{
+    git::Repository,
     mareto::Message,
+    presets::PresetsState,
     state::{DepthLimit, FilterOptions, Options},
 };
 use iced::{
     widget::{
-        column, container, pick_list, row, rule::Rule, scrollable, text, text_input, toggler,
+        button, column, container, pick_list, row, rule::Rule, scrollable, text, text_input,
+        toggler,
     },
-    Element, Length, Padding,
+    Alignment, Element, Length, Padding,
 };
 
 use super::{components::toggle_button, themes::ErrorTextColor};
@@ -14,7 +17,14 @@ use super::{components::toggle_button, themes::ErrorTextColor};
 pub fn options<'a>(
     options: &'a Options,
     filter_options: &'a FilterOptions,
+    presets: &'a PresetsState,
+    repositories: &'a [Repository],
 ) -> Element<'a, Message> {
+    let git_warning_text = if options.use_git && repositories.is_empty() {
+        "The opened folders are not in a git repository"
+    } else {
+        ""
+    };
     let regex_error_text = match &filter_options.filter_input.state.regex {
         Some(Err((_, text))) => text,
         _ => "",
@@ -24,6 +34,7 @@ pub fn options<'a>(
             column![
                 row![
                     text_input("Filter input", &filter_options.filter_input.state.input)
+                        .id(filter_input())
                         .on_input(Message::FilterUpdated)
                         .padding(12)
                         .width(Length::Fill),
@@ -39,6 +50,7 @@ pub fn options<'a>(
                     ),
                 ],
                 text(regex_error_text).style(ErrorTextColor),
+                dropped_entries(filter_options),
             ],
             Rule::horizontal(1),
             depth_control(
@@ -64,6 +76,11 @@ pub fn options<'a>(
                 filter_options.show_folders.state,
                 Message::ShowFoldersToggled
             ),
+            toggler(
+                Some("Show only changed".to_owned()),
+                options.show_only_changed,
+                Message::ShowOnlyChangedToggled
+            ),
             Rule::horizontal(1),
             pick_list(
                 &options.sorting.options[..],
@@ -72,6 +89,23 @@ pub fn options<'a>(
             )
             .width(Length::Fill)
             .padding(12),
+            row![
+                text("Then by"),
+                pick_list(
+                    &options.secondary_sorting.options[..],
+                    options.secondary_sorting.selected,
+                    Message::SecondarySortOrderSelected
+                )
+                .width(Length::Fill)
+                .padding(12),
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+            toggler(
+                Some("Directories first".to_owned()),
+                options.directories_first,
+                Message::DirectoriesFirstToggled
+            ),
             pick_list(
                 &options.display_type.options[..],
                 options.display_type.selected,
@@ -90,6 +124,22 @@ pub fn options<'a>(
                 options.preview_changes,
                 Message::PreviewChangesToggled
             ),
+            toggler(
+                Some("Allow moves outside the opened folder".to_owned()),
+                options.allow_outside_root,
+                Message::AllowOutsideRootToggled
+            ),
+            toggler(
+                Some("Watch folders for changes".to_owned()),
+                options.watch_folders,
+                Message::WatchFoldersToggled
+            ),
+            toggler(
+                Some("Rename tracked files with git mv".to_owned()),
+                options.use_git,
+                Message::UseGitToggled
+            ),
+            text(git_warning_text),
             Rule::horizontal(1),
             pick_list(
                 &options.theme.options[..],
@@ -98,6 +148,8 @@ pub fn options<'a>(
             )
             .width(Length::Fill)
             .padding(12),
+            Rule::horizontal(1),
+            presets_section(presets),
         ]
         .spacing(12)
         .padding(Padding::from([0, 20, 12, 0])),
@@ -105,6 +157,30 @@ pub fn options<'a>(
     .into()
 }
 
+pub fn filter_input() -> text_input::Id {
+    text_input::Id::new("filter")
+}
+
+/// Tells that only dropped entries are shown, with a way to show all of them again.
+fn dropped_entries(filter_options: &FilterOptions) -> Element<'_, Message> {
+    match &filter_options.dropped.state {
+        Some(paths) => row![
+            text(format!(
+                "Only the {} dropped entries are shown",
+                paths.len()
+            ))
+            .width(Length::Fill),
+            button("Show all")
+                .padding(12)
+                .on_press(Message::DroppedEntriesCleared),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center)
+        .into(),
+        None => column![].into(),
+    }
+}
+
 fn depth_control<'a, F1, F2>(
     label: String,
     state: &DepthLimit,
@@ -132,3 +208,45 @@ where
     .spacing(8)
     .into()
 }
+
+/// Saving stores the current filters, sorting, display type and find and replace under the
+/// entered name. Renaming gives the selected preset the entered name.
+fn presets_section(presets: &PresetsState) -> Element<'_, Message> {
+    let name = presets.name.trim();
+    let has_selection = presets.selected.is_some();
+    column![
+        row![
+            pick_list(
+                presets.presets.names(),
+                presets.selected.clone(),
+                Message::PresetSelected
+            )
+            .placeholder("Presets")
+            .width(Length::Fill)
+            .padding(12),
+            button("Load")
+                .padding(12)
+                .on_press_maybe(has_selection.then_some(Message::LoadPreset)),
+            button("Delete")
+                .padding(12)
+                .on_press_maybe(has_selection.then_some(Message::DeletePreset)),
+        ]
+        .spacing(8),
+        row![
+            text_input("Preset name", &presets.name)
+                .on_input(Message::PresetNameUpdated)
+                .on_submit(Message::SavePreset)
+                .padding(12)
+                .width(Length::Fill),
+            button("Save")
+                .padding(12)
+                .on_press_maybe((!name.is_empty()).then_some(Message::SavePreset)),
+            button("Rename").padding(12).on_press_maybe(
+                (has_selection && !name.is_empty()).then_some(Message::RenamePreset)
+            ),
+        ]
+        .spacing(8),
+    ]
+    .spacing(8)
+    .into()
+}
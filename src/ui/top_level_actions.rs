@@ -1,18 +1,59 @@
 use iced::{
-    widget::{button, column},
+    widget::{button, column, pick_list, row, text},
     Element, Length,
 };
 
-use crate::mareto::Message;
+use crate::mareto::{Error, Message};
 
-pub fn top_level_actions() -> Element<'static, Message> {
-    let open_folder_button = top_level_button("Open folder", Message::OpenFolder);
+use super::themes::ErrorTextColor;
 
-    let apply_changes_button = top_level_button("Apply changes", Message::ApplyChanges);
+pub fn top_level_actions<'a>(
+    recent_folders: &'a [String],
+    error: Option<&'a Error>,
+) -> Element<'a, Message> {
+    let open_folder_button = row![
+        top_level_button("Open folder", Message::OpenFolder),
+        top_level_button("Add folder", Message::AddFolder),
+        pick_list(
+            recent_folders,
+            None::<String>,
+            Message::RecentFolderSelected
+        )
+        .placeholder("Recent")
+        .width(Length::Fixed(120.0))
+        .padding(12),
+    ]
+    .spacing(12);
 
-    column![open_folder_button, apply_changes_button,]
-        .spacing(12)
-        .into()
+    let apply_changes_button = row![
+        top_level_button("Apply changes", Message::ApplyChanges),
+        top_level_button("Refresh", Message::Refresh),
+    ]
+    .spacing(12);
+
+    let session_buttons = row![
+        top_level_button("Save session", Message::SaveSession),
+        top_level_button("Open session", Message::OpenSession),
+    ]
+    .spacing(12);
+
+    let mapping_buttons = row![
+        top_level_button("Import mapping", Message::ImportMapping),
+        top_level_button("Export plan", Message::ExportPlan),
+    ]
+    .spacing(12);
+
+    let mut actions = column![
+        open_folder_button,
+        apply_changes_button,
+        session_buttons,
+        mapping_buttons
+    ]
+    .spacing(12);
+    if let Some(error) = error {
+        actions = actions.push(text(error.to_string()).style(ErrorTextColor));
+    }
+    actions.into()
 }
 
 fn top_level_button(label: &str, on_press: Message) -> Element<'_, Message> {
@@ -0,0 +1,54 @@
+use std::{path::Path, time::Duration};
+
+use iced::{
+    futures::{channel::mpsc, SinkExt, StreamExt},
+    subscription, Subscription,
+};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+
+use crate::{
+    fs::Roots,
+    mareto::{Error, Message},
+};
+
+/// How long the folders have to be left alone before a change is reported, so that a program
+/// writing many files causes a single rescan.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Reports [`Message::FoldersChanged`] whenever something changes in one of the opened folders.
+/// The watcher is restarted whenever the opened folders change.
+pub fn watch(roots: Roots) -> Subscription<Message> {
+    subscription::channel(roots.clone(), 8, |mut output| async move {
+        let (sender, mut changes) = mpsc::unbounded();
+        let debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |result: DebounceEventResult| {
+            if result.is_ok_and(|events| !events.is_empty()) {
+                let _ = sender.unbounded_send(());
+            }
+        });
+        // The debouncer stops watching when it's dropped, so it's kept for as long as this runs.
+        let _debouncer = match debouncer.and_then(|mut debouncer| {
+            for folder in &roots.folders {
+                debouncer
+                    .watcher()
+                    .watch(Path::new(folder), RecursiveMode::Recursive)?;
+            }
+            Ok(debouncer)
+        }) {
+            Ok(debouncer) => Some(debouncer),
+            Err(error) => {
+                let _ = output
+                    .send(Message::WatchFailed(Error::Watch(error.to_string())))
+                    .await;
+                None
+            }
+        };
+        loop {
+            match changes.next().await {
+                Some(()) => {
+                    let _ = output.send(Message::FoldersChanged).await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        }
+    })
+}